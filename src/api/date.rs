@@ -0,0 +1,194 @@
+//! The date and time types used by Graph.
+//!
+//! Each keeps the exact string it was read from, which is what's written back out, so a backup
+//! round-trips losslessly, even if Graph sends a value which can't be parsed. The parsed value,
+//! if there is one, is available alongside it.
+
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::timezone::{self, Zone};
+
+/// A date without a time, eg: "2020-01-01".
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/recurrencerange?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "String", into = "String")]
+pub struct Date {
+    raw: String,
+    date: Option<NaiveDate>,
+}
+
+impl Date {
+    /// The date exactly as it was received.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The date, if it could be parsed.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+}
+
+impl From<String> for Date {
+    fn from(raw: String) -> Self {
+        let date = NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").ok();
+
+        Self { raw, date }
+    }
+}
+
+impl From<NaiveDate> for Date {
+    fn from(date: NaiveDate) -> Self {
+        Self { raw: date.format("%Y-%m-%d").to_string(), date: Some(date) }
+    }
+}
+
+impl From<Date> for String {
+    fn from(date: Date) -> Self {
+        date.raw
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// A date and time with an offset from UTC, in ISO 8601 format, eg: "2020-01-01T00:00:00Z".
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "String", into = "String")]
+pub struct DateTimeOffset {
+    raw: String,
+    date_time: Option<DateTime<FixedOffset>>,
+}
+
+impl DateTimeOffset {
+    /// The date and time exactly as it was received.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The date and time, with its offset, if it could be parsed.
+    pub fn date_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.date_time
+    }
+
+    /// The date and time in UTC, if it could be parsed.
+    pub fn utc(&self) -> Option<DateTime<Utc>> {
+        self.date_time.map(|date_time| date_time.with_timezone(&Utc))
+    }
+}
+
+impl From<String> for DateTimeOffset {
+    fn from(raw: String) -> Self {
+        let date_time = DateTime::parse_from_rfc3339(raw.trim()).ok();
+
+        Self { raw, date_time }
+    }
+}
+
+impl From<DateTime<Utc>> for DateTimeOffset {
+    fn from(date_time: DateTime<Utc>) -> Self {
+        Self { raw: date_time.to_rfc3339(), date_time: Some(date_time.into()) }
+    }
+}
+
+impl From<DateTimeOffset> for String {
+    fn from(date_time: DateTimeOffset) -> Self {
+        date_time.raw
+    }
+}
+
+impl fmt::Display for DateTimeOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// Represents a `dateTimeTimeZone` resource type; a local date and time, in a named time zone.
+///
+/// The zone is usually a Windows name, eg: "Pacific Standard Time", but may be an IANA name.
+/// Either is resolved to a zone in the IANA time zone database, if possible, which can then be
+/// used to convert the time to UTC, or to find the IANA name of the zone, eg: "America/Los_Angeles".
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/datetimetimezone?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "RawDateTimeTimeZone", into = "RawDateTimeTimeZone")]
+pub struct DateTimeTimeZone {
+    raw: RawDateTimeTimeZone,
+    local: Option<NaiveDateTime>,
+    zone: Option<Zone>,
+}
+
+/// A `DateTimeTimeZone` as it's sent and received.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RawDateTimeTimeZone {
+    date_time: String,
+    time_zone: String,
+}
+
+impl DateTimeTimeZone {
+    /// Eg: `DateTimeTimeZone::new("2020-01-01T09:30:00.0000000", "Pacific Standard Time")`.
+    pub fn new<D: Into<String>, Z: Into<String>>(date_time: D, time_zone: Z) -> Self {
+        Self::from(RawDateTimeTimeZone { date_time: date_time.into(), time_zone: time_zone.into() })
+    }
+
+    /// The local date and time exactly as it was received, eg: "2020-01-01T09:30:00.0000000".
+    pub fn date_time(&self) -> &str {
+        &self.raw.date_time
+    }
+
+    /// The name of the time zone exactly as it was received, eg: "Pacific Standard Time".
+    pub fn time_zone(&self) -> &str {
+        &self.raw.time_zone
+    }
+
+    /// The local date and time, if it could be parsed.
+    pub fn local(&self) -> Option<NaiveDateTime> {
+        self.local
+    }
+
+    /// The time zone, if it's one we know, including its IANA name.
+    pub fn zone(&self) -> Option<Zone> {
+        self.zone
+    }
+
+    /// The date and time in UTC, if it could be parsed, and its time zone is one we know.
+    pub fn utc(&self) -> Option<DateTime<Utc>> {
+        let utc = self.zone?.local_to_utc(self.local?);
+
+        Some(Utc.from_utc_datetime(&utc))
+    }
+}
+
+impl From<RawDateTimeTimeZone> for DateTimeTimeZone {
+    fn from(raw: RawDateTimeTimeZone) -> Self {
+        let local = NaiveDateTime::parse_from_str(raw.date_time.trim(), "%Y-%m-%dT%H:%M:%S%.f").ok();
+        let zone = timezone::find(&raw.time_zone);
+
+        Self { raw, local, zone }
+    }
+}
+
+impl From<DateTimeTimeZone> for RawDateTimeTimeZone {
+    fn from(date_time: DateTimeTimeZone) -> Self {
+        date_time.raw
+    }
+}
+
+/// Eg: "2020-01-01T09:30:00 (Pacific Standard Time)". The fractional seconds are omitted when they're zero.
+impl fmt::Display for DateTimeTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let date_time = match self.date_time().split_once('.') {
+            Some((whole, fraction)) if fraction.chars().all(|c| c == '0') => whole,
+            _ => self.date_time(),
+        };
+
+        write!(f, "{} ({})", date_time, self.time_zone())
+    }
+}
@@ -1,15 +1,15 @@
+//! The resources of the Graph API which are read and written, as they're sent and received.
 
-use serde::de::Deserialize;
-
-pub type Date = String;
-pub type DateTimeOffset = String;
 pub type StringCollection = Vec<String>;
 
+mod date;
 pub mod user;
 pub mod tasks;
 
-/// Contains the `@odata.context` and `@odata.nextLink` properties of a Collection. 
-/// The latter contains the URL for the next page of results within the Collection. 
+pub use date::{Date, DateTimeOffset, DateTimeTimeZone};
+
+/// Contains the `@odata.context`, `@odata.nextLink` and `@odata.deltaLink` properties of a Collection. 
+/// The `next_link` contains the URL for the next page of results within the Collection. 
 #[derive(Deserialize, Debug, Clone)]
 pub struct OData {
     #[serde(rename = "@odata.context")] 
@@ -17,6 +17,12 @@ pub struct OData {
 
     #[serde(rename = "@odata.nextLink")] 
     pub next_link: Option<String>,
+
+    /// The URL from which to fetch the changes made after this delta query; only given on its last page. 
+    /// 
+    /// See: <https://docs.microsoft.com/en-us/graph/delta-query-overview>
+    #[serde(rename = "@odata.deltaLink")] 
+    pub delta_link: Option<String>,
 }
 
 /// A general collection of results from the Graph API.
@@ -28,53 +34,66 @@ pub struct Collection<T> {
     pub odata: OData,
 }
 
-/// Represents a `dateTimeTimeZone` resource type. 
-/// 
-/// @TODO: This could probably be converted into a `chrono` type with
-/// a custom deserialization process. 
-/// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/datetimetimezone?view=graph-rest-1.0
+/// An item of the results of a delta query, for which only the `id` was selected. 
 #[derive(Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct DateTimeTimeZone {
-    pub date_time: String,
-    pub time_zone: String,
+pub struct DeltaItem {
+    pub id: String,
+
+    /// Present if the item has been deleted, or otherwise removed from the collection, eg: `{ "reason": "deleted" }`. 
+    #[serde(rename = "@removed")] 
+    pub removed: Option<serde_json::Value>,
 }
 
-/// An attempt at a wrapper for handling either a successful response, or a response with an error.
-#[derive(Deserialize, Debug, Clone)]
-#[serde(untagged)]
-pub enum Response<T> {
-    Success(T),
+/// An open extension; untyped data which an application has stored on a resource, such as a task or list. 
+/// 
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/opentypeextension?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Extension {
+    /// The type of the extension, normally `#microsoft.graph.openTypeExtension`.
+    #[serde(rename = "@odata.type")]
+    pub odata_type: Option<String>,
+
+    /// A unique name for the extension, eg: `Com.Contoso.Referral`.
+    pub extension_name: Option<String>,
 
-    Error(ErrorResponse)
+    /// The identifier of the extension; for open extensions, the same as its `extension_name`.
+    pub id: String,
+
+    /// The application's data, exactly as it was stored.
+    #[serde(flatten)]
+    pub data: serde_json::Map<String, serde_json::Value>,
 }
 
-/// An attempt at containing a response error.
+/// The body of an error response from the Graph API.
+/// 
+/// See: <https://docs.microsoft.com/en-us/graph/errors#json-representation>
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
     pub error: ErrorResponseError,
 }
 
-
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponseError {
+    /// An error code string for the error that occurred, eg: `itemNotFound`.
     pub code: String,
 
+    /// A developer ready message about the error that occurred. This should not be displayed to the user directly.
     pub message: String,
 
-    inner_error: ErrorResponseErrorInnerError
+    /// Additional information about the error. Often omitted.
+    pub inner_error: Option<ErrorResponseErrorInnerError>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
 pub struct ErrorResponseErrorInnerError {
-    date: String,
+    pub date: Option<String>,
 
-    request_id: String,
+    #[serde(rename = "request-id")]
+    pub request_id: Option<String>,
 
-    client_request_id: String,
+    #[serde(rename = "client-request-id")]
+    pub client_request_id: Option<String>,
 }
-
@@ -1,10 +1,18 @@
+//! To Do task lists, the tasks within them, and their steps, recurrences, linked resources and attachments.
+//!
+//! See: <https://docs.microsoft.com/en-us/graph/api/resources/todo-overview?view=graph-rest-1.0>
 
-use crate::api::{Date, DateTimeOffset, DateTimeTimeZone};
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::api::{Date, DateTimeOffset, DateTimeTimeZone, Extension, StringCollection};
 
 /// Represents a single Todo List. 
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/todotasklist?view=graph-rest-1.0#properties
-#[derive(Deserialize, Debug)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/todotasklist?view=graph-rest-1.0#properties>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TodoTaskList {
     /// The name of the task list.
@@ -22,22 +30,35 @@ pub struct TodoTaskList {
 
     /// Property indicating the list name if the given list is a well-known list. 
     pub wellknown_list_name: WellknownListName,
+
+    /// The open extensions of the list. 
+    /// These are only returned when requested with `$expand=extensions`.
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+
+    /// Any other properties returned by Graph, which aren't known to this program. 
+    #[serde(flatten)]
+    pub additional_properties: BTreeMap<String, serde_json::Value>,
 }
 
 /// The possible values of a `TodoTaskList` `wellknown_list_name`. 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum WellknownListName {
     None,
     DefaultList,
     FlaggedEmails,
     UnknownFutureValue,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 /// Represents a single Task within a todo list. 
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/todotask?view=graph-rest-1.0
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/todotask?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TodoTask {
     /// The task body that typically contains information about the task.
@@ -47,7 +68,11 @@ pub struct TodoTask {
     /// You can provide a custom time zone in the request header. 
     /// The property value uses ISO 8601 format and is always in UTC time. 
     /// For example, midnight UTC on Jan 1, 2020 would look like this: '2020-01-01T00:00:00Z'.
-    pub body_last_modified_date_time: Option<String>,
+    pub body_last_modified_date_time: Option<DateTimeOffset>,
+
+    /// The categories associated with the task.
+    #[serde(default)]
+    pub categories: StringCollection,
 
     /// The date in the specified time zone that the task was finished.
     pub completed_date_time: Option<DateTimeTimeZone>,
@@ -58,6 +83,10 @@ pub struct TodoTask {
     /// The date in the specified time zone that the task is to be finished.
     pub due_date_time: Option<DateTimeTimeZone>,
 
+    /// Set to true if the task has file attachments.
+    #[serde(default)]
+    pub has_attachments: bool,
+
     /// Unique identifier for the task. By default, this value changes when the item is moved from one list to another.
     pub id: String,
 
@@ -76,17 +105,115 @@ pub struct TodoTask {
     /// The date and time for a reminder alert of the task to occur.
     pub reminder_date_time: Option<DateTimeTimeZone>,
 
+    /// The date in the specified time zone at which the task is scheduled to start.
+    #[serde(default)]
+    pub start_date_time: Option<DateTimeTimeZone>,
+
     /// Indicates the state or progress of the task. 
     pub status: TaskStatus,
 
     /// A brief description of the task.
     pub title: String,
 
-    // TODO: extensions
-    // TODO: linkedResources
+    /// The sub-steps of the task. 
+    /// These are only returned when requested with `$expand=checklistItems`.
+    #[serde(default)]
+    pub checklist_items: Vec<ChecklistItem>,
+
+    /// Links to the items in other applications from which the task was created, eg: an Outlook email. 
+    /// These are only returned when requested with `$expand=linkedResources`.
+    #[serde(default)]
+    pub linked_resources: Vec<LinkedResource>,
+
+    /// The open extensions of the task. 
+    /// These are only returned when requested with `$expand=extensions`.
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+
+    /// The files attached to the task. 
+    /// These aren't returned by Graph with the task; they're only fetched when attachments are downloaded.
+    #[serde(default)]
+    pub attachments: Vec<TaskFileAttachment>,
+
+    /// Any other properties returned by Graph, which aren't known to this program. 
+    /// These are kept exactly as they were received, so that they aren't lost from backups.
+    #[serde(flatten)]
+    pub additional_properties: BTreeMap<String, serde_json::Value>,
+}
+
+/// A sub-step of a `TodoTask`, shown as a checklist beneath it. 
+/// 
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/checklistitem?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItem {
+    /// The title of the step.
+    pub display_name: String,
+
+    /// The unique identifier of the step.
+    pub id: String,
+
+    /// Whether the step has been done.
+    pub is_checked: bool,
+
+    /// The date and time when the step was created.
+    pub created_date_time: DateTimeOffset,
+
+    /// The date and time when the step was finished.
+    pub checked_date_time: Option<DateTimeOffset>,
+}
+
+/// A link to an item in another application which is associated with a `TodoTask`. 
+/// 
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/linkedresource?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedResource {
+    /// The unique identifier of the link.
+    pub id: String,
+
+    /// A deep link to the item in the other application.
+    pub web_url: Option<String>,
+
+    /// The name of the application which the item belongs to.
+    pub application_name: Option<String>,
+
+    /// The title of the item.
+    pub display_name: Option<String>,
+
+    /// The ID of the item in the other application.
+    pub external_id: Option<String>,
+}
+
+/// A file attached to a `TodoTask`. 
+/// 
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/taskfileattachment?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskFileAttachment {
+    /// The unique identifier of the attachment.
+    pub id: String,
+
+    /// The name of the file.
+    pub name: String,
+
+    /// The MIME type of the file.
+    pub content_type: Option<String>,
+
+    /// The length of the file, in bytes.
+    #[serde(default)]
+    pub size: i64,
+
+    /// The date and time when the attachment was last modified.
+    pub last_modified_date_time: Option<DateTimeOffset>,
+
+    /// The SHA-256 hash of the file's content, in hex; the name of the file within the attachments directory. 
+    /// This isn't part of Graph; it's only set once the file has been downloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum TaskStatus {
     NotStarted,
@@ -94,12 +221,29 @@ pub enum TaskStatus {
     Completed,
     WaitingOnOthers,
     Deferred,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotStarted => "Not started",
+            Self::InProgress => "In progress",
+            Self::Completed => "Completed",
+            Self::WaitingOnOthers => "Waiting on others",
+            Self::Deferred => "Deferred",
+            Self::Unknown(status) => status,
+        })
+    }
 }
 
 /// Represents properties of the body of an item, such as a message, event or group post.
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/itembody?view=graph-rest-1.0
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/itembody?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ItemBody {
     /// The content of the item.
@@ -110,26 +254,45 @@ pub struct ItemBody {
 }
 
 /// The possible values of `content_type` for an `ItemBody`.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum BodyType {
     Text,
     Html,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 /// The possible `importance` values for a `TodoTask`. 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum Importance {
     Low,
     Normal,
-    High
+    High,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl fmt::Display for Importance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Low => "Low",
+            Self::Normal => "Normal",
+            Self::High => "High",
+            Self::Unknown(importance) => importance,
+        })
+    }
 }
 
 /// The recurrence pattern and range for a `TodoTask`. 
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/patternedrecurrence?view=graph-rest-1.0
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/patternedrecurrence?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PatternedRecurrence {
     /// The frequency of an event.
@@ -139,20 +302,29 @@ pub struct PatternedRecurrence {
     pub range: RecurrenceRange,
 }
 
+/// A human readable summary of the recurrence, eg: "Every 2 weeks on Monday, Wednesday, starting 2021-05-03".
+impl fmt::Display for PatternedRecurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.pattern, self.range)
+    }
+}
+
 /// Describes the frequency by which a recurrning `TodoTask` repeats. 
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/recurrencepattern?view=graph-rest-1.0
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/recurrencepattern?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type")]
 pub enum RecurrencePattern {
     /// Event repeats based on the number of days specified by *interval* between occurrences.
+    #[serde(rename_all = "camelCase")]
     Daily { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32 
     },
 
     /// Event repeats on the same day or days of the week, based on the number of weeks between each set of occurrences.
+    #[serde(rename_all = "camelCase")]
     Weekly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -165,6 +337,7 @@ pub enum RecurrencePattern {
     },
 
     /// Event repeats on the specified day of the month (e.g. the 15th), based on the number of months between occurrences.
+    #[serde(rename_all = "camelCase")]
     AbsoluteMonthly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -174,16 +347,23 @@ pub enum RecurrencePattern {
     },
     
     /// Event repeats on the specified day or days of the week, in the same relative position in the month, based on the number of months between occurrences.
+    #[serde(rename_all = "camelCase")]
     RelativeMonthly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
 
         /// A collection of the days of the week on which the event occurs. 
         /// If `days_of_week` specifies more than one day, the event falls on the first day that satisfies the pattern. 
-        days_of_week: Vec<DayOfWeek> 
+        days_of_week: Vec<DayOfWeek>,
+
+        /// Specifies on which instance of the allowed days specified in `days_of_week` the event occurs, 
+        /// counted from the first instance in the month. Default is `First`.
+        #[serde(default)]
+        index: WeekIndex,
     },
 
     /// Event repeats on the specified day and month, based on the number of years between occurrences.
+    #[serde(rename_all = "camelCase")]
     AbsoluteYearly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -196,6 +376,7 @@ pub enum RecurrencePattern {
     },
 
     /// Event repeats on the specified day or days of the week, in the same relative position in a specific month of the year, based on the number of years between occurrences.
+    #[serde(rename_all = "camelCase")]
     RelativeYearly { 
         /// The number of units between occurrences, where units can be in days, weeks, months, or years, depending on the type. Required.
         interval: i32, 
@@ -205,11 +386,62 @@ pub enum RecurrencePattern {
         days_of_week: Vec<DayOfWeek>, 
 
         /// The month in which the event occurs. This is a number from 1 to 12.
-        month: i32 
+        month: i32,
+
+        /// Specifies on which instance of the allowed days specified in `days_of_week` the event occurs, 
+        /// counted from the first instance in the month. Default is `First`.
+        #[serde(default)]
+        index: WeekIndex,
+    },
+
+    /// A pattern which isn't known to this program, kept as it was received, including its `type`.
+    #[serde(untagged)]
+    Unknown(serde_json::Map<String, serde_json::Value>),
+}
+
+/// "Every day", "Every 2 days", etc. 
+fn every(interval: i32, unit: &str) -> String {
+    match interval {
+        1 => format!("Every {}", unit),
+        n => format!("Every {} {}s", n, unit),
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// The name of the given `month`, from 1 to 12.
+fn month_name(month: i32) -> &'static str {
+    const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+
+    usize::try_from(month - 1).ok().and_then(|i| MONTHS.get(i)).copied().unwrap_or("an unknown month")
+}
+
+fn join_days(days: &[DayOfWeek], separator: &str) -> String {
+    days.iter().map(|day| day.to_string()).collect::<Vec<_>>().join(separator)
+}
+
+impl fmt::Display for RecurrencePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Daily { interval } => write!(f, "{}", every(*interval, "day")),
+            Self::Weekly { interval, days_of_week, .. } => write!(f, "{} on {}", every(*interval, "week"), join_days(days_of_week, ", ")),
+            Self::AbsoluteMonthly { interval, day_of_month } => write!(f, "{} on day {}", every(*interval, "month"), day_of_month),
+            Self::RelativeMonthly { interval, days_of_week, index } => {
+                write!(f, "{} on the {} {}", every(*interval, "month"), index, join_days(days_of_week, " or "))
+            },
+            Self::AbsoluteYearly { interval, day_of_month, month } => {
+                write!(f, "{} on {} {}", every(*interval, "year"), month_name(*month), day_of_month)
+            },
+            Self::RelativeYearly { interval, days_of_week, month, index } => {
+                write!(f, "{} on the {} {} of {}", every(*interval, "year"), index, join_days(days_of_week, " or "), month_name(*month))
+            },
+            Self::Unknown(pattern) => match pattern.get("type").and_then(|kind| kind.as_str()) {
+                Some(kind) => write!(f, "Repeats ({})", kind),
+                None => f.write_str("Repeats"),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum DayOfWeek {
     Sunday,
@@ -219,16 +451,65 @@ pub enum DayOfWeek {
     Thursday,
     Friday,
     Saturday,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl fmt::Display for DayOfWeek {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sunday => "Sunday",
+            Self::Monday => "Monday",
+            Self::Tuesday => "Tuesday",
+            Self::Wednesday => "Wednesday",
+            Self::Thursday => "Thursday",
+            Self::Friday => "Friday",
+            Self::Saturday => "Saturday",
+            Self::Unknown(day) => day,
+        })
+    }
+}
+
+/// The week of the month in which a relative `RecurrencePattern` occurs. 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum WeekIndex {
+    #[default]
+    First,
+    Second,
+    Third,
+    Fourth,
+    Last,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl fmt::Display for WeekIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::First => "first",
+            Self::Second => "second",
+            Self::Third => "third",
+            Self::Fourth => "fourth",
+            Self::Last => "last",
+            Self::Unknown(index) => index,
+        })
+    }
 }
 
 /// Describes a date range over which a recurring `TodoTask` repeats.
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/recurrencerange?view=graph-rest-1.0
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/recurrencerange?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type")]
 pub enum RecurrenceRange {
     /// Event repeats on all the days that fit the corresponding recurrence pattern between the `start_date` and `end_date` inclusive.
+    #[serde(rename_all = "camelCase")]
     EndDate { 
         /// The date to start applying the recurrence pattern. 
         /// The first occurrence of the meeting may be this date or later, 
@@ -247,6 +528,7 @@ pub enum RecurrenceRange {
     },
 
     /// Event repeats on all the days that fit the corresponding recurrence pattern beginning on the `start_date`.
+    #[serde(rename_all = "camelCase")]
     NoEnd { 
         /// The date to start applying the recurrence pattern. 
         /// The first occurrence of the meeting may be this date or later, 
@@ -260,6 +542,7 @@ pub enum RecurrenceRange {
     },
 
     /// Event repeats for the `number_of_occurrences` based on the recurrence pattern beginning on the `start_date`.
+    #[serde(rename_all = "camelCase")]
     Numbered { 
         /// The date to start applying the recurrence pattern. 
         /// The first occurrence of the meeting may be this date or later, 
@@ -275,4 +558,217 @@ pub enum RecurrenceRange {
         /// If not specified, the time zone of the event is used.
         recurrence_time_zone: Option<String> 
     },
-}
\ No newline at end of file
+
+    /// A range which isn't known to this program, kept as it was received, including its `type`.
+    #[serde(untagged)]
+    Unknown(serde_json::Map<String, serde_json::Value>),
+}
+
+impl fmt::Display for RecurrenceRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EndDate { start_date, end_date, .. } => write!(f, "from {} until {}", start_date, end_date),
+            Self::NoEnd { start_date, .. } => write!(f, "starting {}", start_date),
+            Self::Numbered { start_date, number_of_occurrences, .. } => write!(f, "starting {}, {} times", start_date, number_of_occurrences),
+            Self::Unknown(range) => match range.get("startDate").and_then(|start| start.as_str()) {
+                Some(start_date) => write!(f, "starting {}", start_date),
+                None => f.write_str("over an unknown range"),
+            },
+        }
+    }
+}
+
+/// Tasks and lists for tests, made up of only the properties each test cares about.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// The JSON of a task with the given `properties`, in addition to, or replacing, those every task has.
+    pub fn task_json(properties: Value) -> Value {
+        let mut task = json!({
+            "id": "AAMkAGI1",
+            "title": "Buy milk",
+            "body": { "content": "", "contentType": "text" },
+            "importance": "normal",
+            "status": "notStarted",
+            "isReminderOn": false,
+            "createdDateTime": "2021-03-01T10:00:00Z",
+            "lastModifiedDateTime": "2021-03-01T10:00:00Z",
+        });
+
+        task.as_object_mut().unwrap().extend(properties.as_object().unwrap().clone());
+        task
+    }
+
+    /// A task with the given `properties`, in addition to, or replacing, those every task has.
+    pub fn task(properties: Value) -> TodoTask {
+        serde_json::from_value(task_json(properties)).expect("the task should deserialize")
+    }
+
+    /// A list of the user's own, named `name`.
+    pub fn list(name: &str) -> TodoTaskList {
+        serde_json::from_value(json!({
+            "id": "AAMkAGI2",
+            "displayName": name,
+            "isOwner": true,
+            "isShared": false,
+            "wellknownListName": "none",
+        })).expect("the list should deserialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::api::Collection;
+
+    /// A page of tasks, with one task made up of the given properties.
+    fn page(task: Value) -> Value {
+        json!({
+            "@odata.context": "https://graph.microsoft.com/beta/$metadata#Collection(todoTask)",
+            "value": [fixtures::task_json(task)],
+        })
+    }
+
+    fn parse(task: Value) -> TodoTask {
+        let mut collection: Collection<TodoTask> = serde_json::from_value(page(task)).expect("the page should deserialize");
+        collection.value.remove(0)
+    }
+
+    #[test]
+    fn known_values_are_unchanged() {
+        let task = parse(json!({ "importance": "high", "status": "waitingOnOthers", "body": { "content": "<p>Hi</p>", "contentType": "html" } }));
+
+        assert!(matches!(task.importance, Importance::High));
+        assert!(matches!(task.status, TaskStatus::WaitingOnOthers));
+        assert!(matches!(task.body.content_type, BodyType::Html));
+    }
+
+    #[test]
+    fn unknown_values_round_trip() {
+        let task = parse(json!({
+            "importance": "urgent",
+            "status": "blocked",
+            "body": { "content": "Hi", "contentType": "markdown" },
+        }));
+
+        assert!(matches!(&task.importance, Importance::Unknown(value) if value == "urgent"));
+        assert!(matches!(&task.status, TaskStatus::Unknown(value) if value == "blocked"));
+        assert!(matches!(&task.body.content_type, BodyType::Unknown(value) if value == "markdown"));
+
+        let serialized = serde_json::to_value(&task).unwrap();
+
+        assert_eq!(serialized["importance"], "urgent");
+        assert_eq!(serialized["status"], "blocked");
+        assert_eq!(serialized["body"]["contentType"], "markdown");
+    }
+
+    #[test]
+    fn unknown_recurrence_values_round_trip() {
+        let task = parse(json!({
+            "recurrence": {
+                "pattern": { "type": "relativeMonthly", "interval": 1, "daysOfWeek": ["friday", "someday"], "index": "fifth" },
+                "range": { "type": "untilDone", "startDate": "2021-05-01", "recurrenceTimeZone": "UTC" },
+            },
+        }));
+
+        let recurrence = task.recurrence.as_ref().expect("the task should recur");
+
+        match &recurrence.pattern {
+            RecurrencePattern::RelativeMonthly { days_of_week, index, .. } => {
+                assert!(matches!(days_of_week.as_slice(), [DayOfWeek::Friday, DayOfWeek::Unknown(day)] if day == "someday"));
+                assert!(matches!(index, WeekIndex::Unknown(index) if index == "fifth"));
+            },
+            pattern => panic!("expected a relative monthly pattern, found {:?}", pattern),
+        }
+
+        assert!(matches!(&recurrence.range, RecurrenceRange::Unknown(range) if range["type"] == "untilDone"));
+
+        let serialized = serde_json::to_value(&task).unwrap();
+
+        assert_eq!(serialized["recurrence"]["pattern"]["daysOfWeek"], json!(["friday", "someday"]));
+        assert_eq!(serialized["recurrence"]["pattern"]["index"], "fifth");
+        assert_eq!(serialized["recurrence"]["range"], json!({ "type": "untilDone", "startDate": "2021-05-01", "recurrenceTimeZone": "UTC" }));
+    }
+
+    #[test]
+    fn unknown_recurrence_patterns_round_trip() {
+        let pattern = json!({ "type": "hourly", "interval": 3, "minute": 15 });
+        let task = parse(json!({
+            "recurrence": {
+                "pattern": pattern,
+                "range": { "type": "noEnd", "startDate": "2021-05-01", "recurrenceTimeZone": null },
+            },
+        }));
+
+        let recurrence = task.recurrence.as_ref().expect("the task should recur");
+
+        assert!(matches!(&recurrence.pattern, RecurrencePattern::Unknown(_)));
+        assert!(matches!(&recurrence.range, RecurrenceRange::NoEnd { .. }));
+        assert_eq!(recurrence.to_string(), "Repeats (hourly), starting 2021-05-01");
+
+        assert_eq!(serde_json::to_value(&task).unwrap()["recurrence"]["pattern"], pattern);
+    }
+
+    #[test]
+    fn dates_are_parsed_and_round_trip() {
+        let task = parse(json!({
+            "createdDateTime": "2021-03-01T10:00:00.1234567Z",
+            "bodyLastModifiedDateTime": "0001-01-01T00:00:00",
+            "dueDateTime": { "dateTime": "2021-07-01T09:30:00.0000000", "timeZone": "Pacific Standard Time" },
+            "reminderDateTime": { "dateTime": "2021-07-01T09:30:00.0000000", "timeZone": "Europe/Berlin" },
+            "completedDateTime": { "dateTime": "not a date", "timeZone": "Mars Standard Time" },
+        }));
+
+        let created = task.created_date_time.utc().expect("the creation date should parse");
+        assert_eq!(created.to_rfc3339(), "2021-03-01T10:00:00.123456700+00:00");
+
+        // Graph sends this without an offset when the body has never been modified.
+        let body_modified = task.body_last_modified_date_time.as_ref().unwrap();
+        assert!(body_modified.utc().is_none());
+
+        let due = task.due_date_time.as_ref().unwrap();
+        assert_eq!(due.zone().map(|zone| zone.iana), Some("America/Los_Angeles"));
+        assert_eq!(due.utc().map(|utc| utc.to_rfc3339()).as_deref(), Some("2021-07-01T16:30:00+00:00"));
+
+        let reminder = task.reminder_date_time.as_ref().unwrap();
+        assert_eq!(reminder.zone().map(|zone| zone.name), Some("W. Europe Standard Time"));
+        assert_eq!(reminder.utc().map(|utc| utc.to_rfc3339()).as_deref(), Some("2021-07-01T07:30:00+00:00"));
+
+        let completed = task.completed_date_time.as_ref().unwrap();
+        assert!(completed.local().is_none() && completed.zone().is_none() && completed.utc().is_none());
+
+        let serialized = serde_json::to_value(&task).unwrap();
+
+        assert_eq!(serialized["createdDateTime"], "2021-03-01T10:00:00.1234567Z");
+        assert_eq!(serialized["bodyLastModifiedDateTime"], "0001-01-01T00:00:00");
+        assert_eq!(serialized["dueDateTime"], json!({ "dateTime": "2021-07-01T09:30:00.0000000", "timeZone": "Pacific Standard Time" }));
+        assert_eq!(serialized["reminderDateTime"]["timeZone"], "Europe/Berlin");
+        assert_eq!(serialized["completedDateTime"], json!({ "dateTime": "not a date", "timeZone": "Mars Standard Time" }));
+    }
+
+    #[test]
+    fn unknown_list_names_round_trip() {
+        let collection: Collection<TodoTaskList> = serde_json::from_value(json!({
+            "value": [{ "id": "L1", "displayName": "Pinned", "isOwner": true, "isShared": false, "wellknownListName": "pinnedList" }],
+        })).expect("the page should deserialize");
+
+        let list = &collection.value[0];
+
+        assert!(matches!(&list.wellknown_list_name, WellknownListName::Unknown(name) if name == "pinnedList"));
+        assert_eq!(serde_json::to_value(list).unwrap()["wellknownListName"], "pinnedList");
+    }
+
+    #[test]
+    fn attachments_without_a_size_are_read() {
+        let collection: Collection<TaskFileAttachment> = serde_json::from_value(json!({
+            "value": [{ "id": "A/1=", "name": "notes.txt", "contentType": "text/plain" }],
+        })).expect("the page should deserialize");
+
+        assert_eq!(collection.value[0].size, 0);
+    }
+}
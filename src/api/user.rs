@@ -1,11 +1,11 @@
+//! The signed in user, whose lists are exported.
 
-pub type DateTimeOffset = String;
-pub type StringCollection = Vec<String>;
+use crate::api::{DateTimeOffset, StringCollection};
 
 /// Represents a `User` resource type.
 /// 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#properties
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#properties>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
     /// A freeform text entry field for the user to describe themselves.
@@ -16,7 +16,7 @@ pub struct User {
 
     /// Sets the age group of the user. 
     /// 
-    /// See: https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#legal-age-group-property-definitions
+    /// See: <https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#legal-age-group-property-definitions>
     pub age_group: Option<AgeGroup>,
 
     // TODO: assignedLicenses
@@ -30,7 +30,7 @@ pub struct User {
     /// only one number can be set for this property.
     /// 
     /// Read-only for users synced from on-premises directory. Returned by default.
-    pub business_phones: Option<Vec<String>>,
+    pub business_phones: Option<StringCollection>,
 
     /// The city in which the user is located. Maximum length is 128 characters.
     pub city: Option<String>,
@@ -43,7 +43,7 @@ pub struct User {
 
     /// Sets whether consent has been obtained for minors.
     /// 
-    /// See: https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#legal-age-group-property-definitions
+    /// See: <https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#legal-age-group-property-definitions>
     pub consent_provided_for_minor: Option<ConsentProvidedForMinor>,
 
     /// The country/region in which the user is located; for example, "US" or "UK". Maximum length is 128 characters.
@@ -119,10 +119,10 @@ pub struct User {
     pub identities: Option<Vec<ObjectIdentity>>,
 
     /// The instant message voice over IP (VOIP) session initiation protocol (SIP) addresses for the user. Read-only.
-    pub im_addresses: Option<Vec<String>>,
+    pub im_addresses: Option<StringCollection>,
 
     /// A list for the user to describe their interests.
-    pub interests: Option<Vec<String>>,
+    pub interests: Option<StringCollection>,
 
     /// Do not use – reserved for future use.
     pub is_resource_account: Option<bool>,
@@ -184,9 +184,9 @@ pub struct User {
     /// Contains the on-premises userPrincipalName synchronized from the on-premises directory. The property is only populated for customers who are synchronizing their on-premises directory to Azure Active Directory via Azure AD Connect. Read-only.
     pub on_premises_user_principal_name: Option<String>,
 
-    /// A list of additional email addresses for the user; for example: ["bob@contoso.com", "Robert@fabrikam.com"].
+    /// A list of additional email addresses for the user; for example: `["bob@contoso.com", "Robert@fabrikam.com"]`.
     /// NOTE: While this property can contain accent characters, they can cause access issues to first-party applications for the user. 
-    pub other_mails: Option<Vec<String>>,
+    pub other_mails: Option<StringCollection>,
 
     /// Specifies password policies for the user. This value is an enumeration with one possible value being “DisableStrongPassword”, which allows weaker passwords than the default policy to be specified. “DisablePasswordExpiration” can also be specified. The two may be specified together; for example: "DisablePasswordExpiration, DisableStrongPassword".
     /// 
@@ -196,7 +196,7 @@ pub struct User {
     // TODO: passwordProfile
 
     /// A list for the user to enumerate their past projects.
-    pub past_projects: Option<Vec<String>>,
+    pub past_projects: Option<StringCollection>,
 
     /// The postal code for the user's postal address. The postal code is specific to the user's country/region. In the United States of America, this attribute contains the ZIP code. Maximum length is 40 characters.
     pub postal_code: Option<String>,
@@ -209,23 +209,23 @@ pub struct User {
 
     // TODO: provisionedPlans 
 
-    /// For example: ["SMTP: bob@contoso.com", "smtp: bob@sales.contoso.com"] The any operator is required for filter expressions on multi-valued properties. Read-only, Not nullable.
-    pub proxy_addresses: Option<Vec<String>>,
+    /// For example: `["SMTP: bob@contoso.com", "smtp: bob@sales.contoso.com"]` The any operator is required for filter expressions on multi-valued properties. Read-only, Not nullable.
+    pub proxy_addresses: Option<StringCollection>,
 
     /// Any refresh tokens or sessions tokens (session cookies) issued before this time are invalid, and applications will get an error when using an invalid refresh or sessions token to acquire a delegated access token (to access APIs such as Microsoft Graph). If this happens, the application will need to acquire a new refresh token by making a request to the authorize endpoint. 
     pub refresh_tokens_valid_from_date_time: Option<String>,
 
     /// A list for the user to enumerate their responsibilities.
-    pub responsibilities: Option<Vec<String>>,
+    pub responsibilities: Option<StringCollection>,
 
     /// A list for the user to enumerate the schools they have attended.
-    pub schools: Option<Vec<String>>,
+    pub schools: Option<StringCollection>,
 
     /// true if the Outlook global address list should contain this user, otherwise false. If not set, this will be treated as true. For users invited through the invitation manager, this property will be set to false.
     pub show_in_address_list: Option<bool>,
 
     /// A list for the user to enumerate their skills.
-    pub skills: Option<Vec<String>>,
+    pub skills: Option<StringCollection>,
 
     /// Any refresh tokens or sessions tokens (session cookies) issued before this time are invalid, and applications will get an error when using an invalid refresh or sessions token to acquire a delegated access token (to access APIs such as Microsoft Graph). If this happens, the application will need to acquire a new refresh token by making a request to the authorize endpoint. Read-only. Use revokeSignInSessions to reset.
     pub sign_in_sessions_valid_from_date_time: Option<DateTimeOffset>,
@@ -254,25 +254,33 @@ pub struct User {
     pub user_type: Option<String>,
 }
 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#agegroup-values
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/user?view=graph-rest-1.0#agegroup-values>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum AgeGroup {
     Minor,
     NotAdult,
-    Adult
+    Adult,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum ConsentProvidedForMinor {
     Granted,
     Denied,
-    NotRequired
+    NotRequired,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum LegalAgeGroupClassification {
     MinorWithOutParentalConsent, 
@@ -280,10 +288,14 @@ pub enum LegalAgeGroupClassification {
     MinorNoParentalConsentRequired, 
     NotAdult,
     Adult,
+
+    /// A value which isn't known to this program, kept as it was received.
+    #[serde(untagged)]
+    Unknown(String),
 }
 
-/// See: https://docs.microsoft.com/en-us/graph/api/resources/objectidentity?view=graph-rest-1.0
-#[derive(Deserialize, Debug, Clone)]
+/// See: <https://docs.microsoft.com/en-us/graph/api/resources/objectidentity?view=graph-rest-1.0>
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ObjectIdentity {
     pub sign_in_type: String,
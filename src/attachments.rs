@@ -0,0 +1,154 @@
+//! Downloading the files attached to tasks.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use crate::api::Collection;
+use crate::api::tasks::{TaskFileAttachment, TodoTask};
+use crate::batch;
+use crate::client::{self, GraphClient};
+use crate::error::Result;
+use crate::reader::CollectionReader;
+
+/// Called as each attachment starts being downloaded; lists may be downloaded from several threads at once.
+type Progress = Box<dyn Fn(&TaskFileAttachment) + Send + Sync>;
+
+/// A directory of downloaded attachments, each named by the SHA-256 hash of its content.
+///
+/// Identical files, whether attached to several tasks or downloaded by an earlier export, are only stored once.
+pub struct AttachmentStore {
+    directory: PathBuf,
+
+    progress: Option<Progress>,
+}
+
+impl AttachmentStore {
+    /// Store attachments in the given `directory`, creating it if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(Self { directory, progress: None })
+    }
+
+    /// Call `progress` as each attachment starts being downloaded.
+    pub fn with_progress<F: Fn(&TaskFileAttachment) + Send + Sync + 'static>(mut self, progress: F) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Download every attachment of each of the `tasks` which has any, within the list with the given ID,
+    /// recording their metadata, including the hash of each file, against their task.
+    ///
+    /// The attachments of every task are listed in batches, rather than a request per task,
+    /// but the content of each is still downloaded on its own, as it would be base64 encoded in a batch.
+    ///
+    /// See: <https://docs.microsoft.com/en-us/graph/api/todotask-list-attachments?view=graph-rest-1.0>
+    pub fn download(&self, client: &GraphClient, list_id: &str, tasks: &mut [TodoTask]) -> Result<()> {
+        let path = |task_id: &str| format!("/me/todo/lists/{}/tasks/{}/attachments", client::encode_segment(list_id), client::encode_segment(task_id));
+
+        // The content is excluded, as it would be base64 encoded in the response, in full.
+        let requests = tasks.iter()
+            .filter(|task| task.has_attachments)
+            .map(|task| (task.id.clone(), format!("{}?$select=id,name,contentType,size,lastModifiedDateTime", path(&task.id))))
+            .collect();
+
+        let mut pages = batch::get_each::<Collection<TaskFileAttachment>>(client, requests)?;
+
+        for task in tasks.iter_mut() {
+            let page = match pages.remove(&task.id) {
+                Some(page) => page?,
+                None => continue,
+            };
+
+            // A task is unlikely to have more than a page of attachments, but any further pages are read one by one.
+            let remaining = page.odata.next_link.map(|link| CollectionReader::<TaskFileAttachment>::new(client, link));
+
+            task.attachments = page.value.into_iter()
+                .map(Ok)
+                .chain(remaining.into_iter().flatten())
+                .map(|attachment| {
+                    let mut attachment = attachment?;
+
+                    if let Some(progress) = &self.progress {
+                        progress(&attachment);
+                    }
+
+                    let hash = self.store(client, &client.url(&format!("{}/{}/$value", path(&task.id), client::encode_segment(&attachment.id))))?;
+                    attachment.sha256 = Some(hash);
+
+                    Ok(attachment)
+                })
+                .collect::<Result<_>>()?;
+        }
+
+        Ok(())
+    }
+
+    /// Stream the content at `url` into the store, returning its hash.
+    /// The content is written to a temporary file first, as its hash, and so its name, isn't known until it's complete.
+    fn store(&self, client: &GraphClient, url: &str) -> Result<String> {
+        let temp_path = self.directory.join(format!(".download-{:016x}", rand::random::<u64>()));
+
+        let result = (|| {
+            let mut writer = HashingWriter::new(BufWriter::new(File::create(&temp_path)?));
+
+            client.download(url, &mut writer)?;
+
+            let (mut file, hash) = writer.finish();
+            file.flush()?;
+
+            Ok(hash)
+        })();
+
+        let hash = match result {
+            Ok(hash) => hash,
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                return Err(e);
+            }
+        };
+
+        let path = self.directory.join(&hash);
+
+        if path.exists() {
+            fs::remove_file(&temp_path)?;
+        } else {
+            fs::rename(&temp_path, &path)?;
+        }
+
+        Ok(hash)
+    }
+}
+
+/// Writes to an inner writer, while hashing everything written.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, hasher: Sha256::new() }
+    }
+
+    /// The inner writer, and the hash of everything written to it, as lower case hex.
+    fn finish(self) -> (W, String) {
+        (self.inner, format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
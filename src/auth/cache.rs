@@ -0,0 +1,267 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auth::TokenResponse;
+use crate::error::Result;
+
+/// The name of the directory, within the user's cache directory, in which tokens are stored.
+const CACHE_DIR_NAME: &str = "microsoft-todo-export";
+
+/// An access token, and the refresh token used to renew it, persisted between runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedToken {
+    /// The token used to call Microsoft Graph.
+    pub access_token: String,
+
+    /// The time at which the `access_token` expires, in seconds since the Unix epoch.
+    pub expires_at: u64,
+
+    /// The token used to acquire a new `access_token`, if one was issued.
+    pub refresh_token: Option<String>,
+
+    /// The scopes the `access_token` was granted.
+    /// Tokens cached before these were recorded have none, so they're treated as granting nothing.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl CachedToken {
+    /// Create a cached token from a response from the token endpoint.
+    ///
+    /// If the response doesn't contain a new refresh token, or the granted scopes, those of the `previous` token are kept.
+    pub fn from_response(response: TokenResponse, previous: Option<&CachedToken>) -> Self {
+        Self {
+            access_token: response.access_token,
+            expires_at: unix_now() + response.expires_in,
+            refresh_token: response.refresh_token.or_else(|| previous.and_then(|previous| previous.refresh_token.clone())),
+            scopes: match response.scope {
+                Some(scope) => scope.split_whitespace().map(String::from).collect(),
+                None => previous.map(|previous| previous.scopes.clone()).unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Create a cached token for an access token which was acquired elsewhere, and whose expiry is unknown.
+    pub fn from_access_token(access_token: String) -> Self {
+        Self {
+            access_token,
+            expires_at: u64::MAX,
+            refresh_token: None,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// The `requested` scopes which the access token wasn't granted. 
+    ///
+    /// Scopes are compared regardless of case, and of any resource they're prefixed with (eg: `https://graph.microsoft.com/`), 
+    /// as Microsoft doesn't always return them as they were requested. `offline_access`, and the OpenID Connect scopes, 
+    /// are never returned, as they aren't granted to the access token itself. A `.ReadWrite` scope covers its `.Read` scope.
+    pub fn missing_scopes<'a>(&self, requested: &'a [String]) -> Vec<&'a str> {
+        let name = |scope: &str| scope.rsplit('/').next().unwrap_or(scope).to_lowercase();
+        let granted: Vec<String> = self.scopes.iter().map(|scope| name(scope)).collect();
+
+        let is_granted = |scope: &str| {
+            let scope = name(scope);
+            let read_write = scope.strip_suffix(".read").map(|resource| format!("{}.readwrite", resource));
+
+            granted.contains(&scope) || read_write.is_some_and(|read_write| granted.contains(&read_write))
+        };
+
+        requested.iter()
+            .map(String::as_str)
+            .filter(|scope| !["offline_access", "openid", "profile", "email"].contains(&name(scope).as_str()))
+            .filter(|scope| !is_granted(scope))
+            .collect()
+    }
+
+    /// Will the access token have expired within the next `margin`?
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        unix_now().saturating_add(margin.as_secs()) >= self.expires_at
+    }
+}
+
+/// Stores the `CachedToken` of a single account on disk.
+///
+/// The file is only readable by the current user.
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+}
+
+impl TokenCache {
+    /// The cache for the given `account`, within the user's cache directory.
+    pub fn for_account(account: &str) -> Result<Self> {
+        let cache_dir = dirs::cache_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Unable to find the user's cache directory"))?;
+
+        // Keep the account name from escaping the cache directory.
+        let file_name: String = account.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '@' { c } else { '_' })
+            .collect();
+
+        Ok(Self::at(cache_dir.join(CACHE_DIR_NAME).join("tokens").join(format!("{}.json", file_name))))
+    }
+
+    /// The cache stored at the given `path`.
+    pub fn at<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Read the cached token, if there is one.
+    pub fn load(&self) -> Result<Option<CachedToken>> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // A corrupt cache is treated as an empty one; the user will just have to sign in again.
+        Ok(serde_json::from_slice(&contents).ok())
+    }
+
+    /// Write the `token` to the cache, replacing any existing token.
+    pub fn store(&self, token: &CachedToken) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_private_dir(parent)?;
+        }
+
+        let contents = serde_json::to_vec_pretty(token).map_err(io::Error::from)?;
+
+        // Write to a temporary file first, so a failed write doesn't lose the existing token.
+        let temp_path = self.path.with_extension("json.tmp");
+        let mut file = create_private_file(&temp_path)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// The current time, in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir_all(path)
+}
+
+#[cfg(unix)]
+fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+
+    // `mode` only applies when the file is created, so tighten the permissions of an existing file too.
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new().write(true).create(true).truncate(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn granted(scopes: &[&str]) -> CachedToken {
+        CachedToken { scopes: scopes.iter().map(|s| s.to_string()).collect(), ..CachedToken::from_access_token("token".to_string()) }
+    }
+
+    fn scopes(scopes: &[&str]) -> Vec<String> {
+        scopes.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// A directory for the test with the given `name`, which doesn't exist yet.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("microsoft-todo-export-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn read_only_tokens_are_missing_write_scopes() {
+        let token = granted(&["User.Read", "Tasks.Read"]);
+
+        assert_eq!(token.missing_scopes(&scopes(&["User.Read", "Tasks.ReadWrite", "offline_access"])), vec!["Tasks.ReadWrite"]);
+        assert!(token.missing_scopes(&scopes(&["user.read", "https://graph.microsoft.com/Tasks.Read", "offline_access"])).is_empty());
+    }
+
+    #[test]
+    fn write_scopes_cover_read_scopes() {
+        let token = granted(&["https://graph.microsoft.com/User.Read", "Tasks.ReadWrite", "openid", "profile"]);
+
+        assert!(token.missing_scopes(&scopes(&["User.Read", "Tasks.Read", "offline_access"])).is_empty());
+    }
+
+    #[test]
+    fn tokens_without_scopes_are_missing_every_scope() {
+        let token: CachedToken = serde_json::from_str(r#"{ "access_token": "token", "expires_at": 0, "refresh_token": "refresh" }"#).unwrap();
+
+        assert_eq!(token.missing_scopes(&scopes(&["User.Read", "Tasks.Read", "offline_access"])), vec!["User.Read", "Tasks.Read"]);
+    }
+
+    #[test]
+    fn renewals_keep_the_previous_refresh_token_and_scopes() {
+        let previous = CachedToken { refresh_token: Some("refresh".to_string()), ..granted(&["Tasks.Read"]) };
+        let response = TokenResponse { expires_in: 3600, access_token: "renewed".to_string(), refresh_token: None, scope: None };
+
+        let token = CachedToken::from_response(response, Some(&previous));
+
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(token.scopes, vec!["Tasks.Read"]);
+    }
+
+    #[test]
+    fn stored_tokens_are_loaded() {
+        let dir = temp_dir("stored");
+        let cache = TokenCache::at(dir.join("tokens").join("user.json"));
+
+        assert!(cache.load().unwrap().is_none());
+
+        cache.store(&CachedToken { refresh_token: Some("refresh".to_string()), ..granted(&["Tasks.Read"]) }).unwrap();
+        let token = cache.load().unwrap().expect("the token should have been stored");
+
+        assert_eq!(token.access_token, "token");
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(token.scopes, vec!["Tasks.Read"]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&dir.join("tokens").join("user.json")), 0o600);
+            assert_eq!(mode(&dir.join("tokens")), 0o700);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_tokens_are_not_loaded() {
+        let dir = temp_dir("corrupt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("user.json"), "{ \"access_token\": ").unwrap();
+
+        assert!(TokenCache::at(dir.join("user.json")).load().unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,390 @@
+//! Signing in to Microsoft Graph, with the OAuth 2.0 device code flow, and keeping the access token current.
+
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::error::{Error, Result};
+
+mod cache;
+
+pub use cache::{CachedToken, TokenCache};
+
+/// The Microsoft identity platform.
+pub const DEFAULT_AUTHORITY: &str = "https://login.microsoftonline.com";
+
+/// Allows both work/school and personal Microsoft accounts to sign in.
+pub const DEFAULT_TENANT: &str = "common";
+
+/// The scopes required to read the user's profile and their tasks.
+/// `offline_access` is requested so that a refresh token is issued alongside the access token.
+pub const DEFAULT_SCOPES: &[&str] = &["User.Read", "Tasks.Read", "offline_access"];
+
+/// The grant type used when polling the token endpoint during the device code flow.
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// The grant type used to renew an access token.
+const REFRESH_TOKEN_GRANT_TYPE: &str = "refresh_token";
+
+/// The polling interval to use if the authorization server doesn't specify one.
+const DEFAULT_POLL_INTERVAL: u64 = 5;
+
+/// Access tokens are renewed when they're due to expire within this many seconds,
+/// so that a token doesn't expire between being read and being used.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Configuration of the application used to sign in to Microsoft Graph.
+#[derive(Debug, Clone)]
+pub struct OAuthConfig {
+    /// The Application (client) ID of an Azure AD app registration.
+    /// The app registration must have "Allow public client flows" enabled.
+    pub client_id: String,
+
+    /// The tenant to sign in to; `common`, `organizations`, `consumers`, or a tenant ID or domain.
+    pub tenant: String,
+
+    /// The Graph permissions to request, for example `Tasks.Read`.
+    pub scopes: Vec<String>,
+
+    /// The base URL of the authorization server.
+    /// This may be pointed at a local stand-in token endpoint for testing.
+    pub authority: String,
+}
+
+impl OAuthConfig {
+    /// Create a new configuration for the given `client_id`, using the default tenant, scopes and authority.
+    pub fn new<S: Into<String>>(client_id: S) -> Self {
+        Self {
+            client_id: client_id.into(),
+            tenant: DEFAULT_TENANT.to_string(),
+            scopes: DEFAULT_SCOPES.iter().map(|s| s.to_string()).collect(),
+            authority: DEFAULT_AUTHORITY.to_string(),
+        }
+    }
+
+    /// The URL of the given OAuth2 `endpoint` (eg: `token`) for the configured tenant.
+    fn endpoint_url(&self, endpoint: &str) -> String {
+        format!("{}/{}/oauth2/v2.0/{}", self.authority.trim_end_matches('/'), self.tenant, endpoint)
+    }
+
+    /// The requested scopes as a single space delimited string.
+    fn scope(&self) -> String {
+        self.scopes.join(" ")
+    }
+}
+
+/// The response to a device authorization request.
+///
+/// See: <https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-oauth2-device-code#device-authorization-response>
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeviceCode {
+    /// The code used to poll the token endpoint. Not shown to the user.
+    pub device_code: String,
+
+    /// The code the user enters on the verification page.
+    pub user_code: String,
+
+    /// The URL the user should visit to sign in.
+    pub verification_uri: String,
+
+    /// The number of seconds before the `device_code` and `user_code` expire.
+    pub expires_in: u64,
+
+    /// The number of seconds to wait between polling requests.
+    pub interval: Option<u64>,
+
+    /// Human readable instructions for the user.
+    pub message: Option<String>,
+}
+
+/// A successful response from the token endpoint.
+///
+/// See: <https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-oauth2-device-code#successful-authentication-response>
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenResponse {
+    /// The number of seconds before the access token expires.
+    pub expires_in: u64,
+
+    /// The token used to call Microsoft Graph.
+    pub access_token: String,
+
+    /// Issued if the `offline_access` scope was granted.
+    pub refresh_token: Option<String>,
+
+    /// The scopes the access token was granted, separated by spaces.
+    pub scope: Option<String>,
+}
+
+/// An error response from the authorization server.
+///
+/// See: <https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-oauth2-device-code#expected-errors>
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenError {
+    /// The error code, for example `authorization_pending` or `expired_token`.
+    pub error: String,
+
+    /// A description of the error.
+    pub error_description: Option<String>,
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error_description {
+            Some(description) => write!(f, "{}: {}", self.error, description),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+/// Either a token, or an error, returned by the authorization server.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum TokenEndpointResponse<T> {
+    Success(T),
+
+    Error(TokenError),
+}
+
+impl<T> TokenEndpointResponse<T> {
+    fn into_result(self) -> Result<T> {
+        match self {
+            Self::Success(value) => Ok(value),
+            Self::Error(e) => Err(Error::AuthError(e)),
+        }
+    }
+}
+
+/// Signs in using the OAuth2 device authorization grant.
+///
+/// The user is given a code to enter at a verification URL on any device,
+/// while this flow polls the token endpoint until they've finished signing in.
+///
+/// See: <https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-oauth2-device-code>
+pub struct DeviceCodeFlow<'a> {
+    client: &'a reqwest::blocking::Client,
+
+    config: &'a OAuthConfig,
+}
+
+impl<'a> DeviceCodeFlow<'a> {
+    pub fn new(client: &'a reqwest::blocking::Client, config: &'a OAuthConfig) -> Self {
+        Self { client, config }
+    }
+
+    /// Request a new device code and user code from the authorization server.
+    pub fn request_code(&self) -> Result<DeviceCode> {
+        self.client
+            .post(self.config.endpoint_url("devicecode"))
+            .form(&[
+                ("client_id", self.config.client_id.as_str()),
+                ("scope", self.config.scope().as_str()),
+            ])
+            .send()?
+            .json::<TokenEndpointResponse<DeviceCode>>()?
+            .into_result()
+    }
+
+    /// Poll the token endpoint until the user has signed in, declined, or the `code` has expired.
+    pub fn poll_token(&self, code: &DeviceCode) -> Result<TokenResponse> {
+        let mut interval = code.interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+
+        loop {
+            thread::sleep(Duration::from_secs(interval));
+
+            let response = self.client
+                .post(self.config.endpoint_url("token"))
+                .form(&[
+                    ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                    ("client_id", self.config.client_id.as_str()),
+                    ("device_code", code.device_code.as_str()),
+                ])
+                .send()?
+                .json::<TokenEndpointResponse<TokenResponse>>()?;
+
+            match response {
+                TokenEndpointResponse::Success(token) => return Ok(token),
+                TokenEndpointResponse::Error(e) if e.error == "authorization_pending" && Instant::now() < deadline => {},
+                TokenEndpointResponse::Error(e) if e.error == "slow_down" && Instant::now() < deadline => {
+                    interval += DEFAULT_POLL_INTERVAL;
+                },
+                TokenEndpointResponse::Error(e) => return Err(Error::AuthError(e)),
+            }
+        }
+    }
+
+    /// Run the full flow, calling `prompt` with the code the user needs to enter.
+    pub fn sign_in<F: FnOnce(&DeviceCode)>(&self, prompt: F) -> Result<TokenResponse> {
+        let code = self.request_code()?;
+
+        prompt(&code);
+
+        self.poll_token(&code)
+    }
+}
+
+/// Exchange a `refresh_token` for a new access token.
+///
+/// See: <https://docs.microsoft.com/en-us/azure/active-directory/develop/v2-oauth2-auth-code-flow#refresh-the-access-token>
+pub async fn refresh_token(client: &reqwest::Client, config: &OAuthConfig, refresh_token: &str) -> Result<TokenResponse> {
+    client
+        .post(config.endpoint_url("token"))
+        .form(&[
+            ("grant_type", REFRESH_TOKEN_GRANT_TYPE),
+            ("client_id", config.client_id.as_str()),
+            ("refresh_token", refresh_token),
+            ("scope", config.scope().as_str()),
+        ])
+        .send().await?
+        .json::<TokenEndpointResponse<TokenResponse>>().await?
+        .into_result()
+}
+
+/// The signed in user's access token, which is renewed whenever it's about to expire.
+///
+/// The token is locked while it's being renewed, so that requests made at the same time wait for the renewed token,
+/// rather than each renewing it.
+pub struct Session {
+    token: Mutex<CachedToken>,
+
+    /// The client and configuration used to renew the token; `None` if the token can't be renewed.
+    renewal: Option<(reqwest::Client, OAuthConfig)>,
+
+    /// Where renewed tokens are saved, if anywhere.
+    cache: Option<TokenCache>,
+}
+
+impl Session {
+    /// Create a session which renews its `token` using the given `config`, saving renewed tokens to the `cache`.
+    pub fn new(token: CachedToken, client: reqwest::Client, config: OAuthConfig, cache: Option<TokenCache>) -> Self {
+        Self {
+            token: Mutex::new(token),
+            renewal: Some((client, config)),
+            cache,
+        }
+    }
+
+    /// Create a session for an access token which was acquired elsewhere, and can't be renewed.
+    pub fn from_access_token<S: Into<String>>(access_token: S) -> Self {
+        Self {
+            token: Mutex::new(CachedToken::from_access_token(access_token.into())),
+            renewal: None,
+            cache: None,
+        }
+    }
+
+    /// Get a current access token, renewing it first if it has expired, or is about to.
+    pub async fn access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+
+        if token.expires_within(EXPIRY_MARGIN) {
+            if let (Some((client, config)), Some(refresh)) = (&self.renewal, token.refresh_token.clone()) {
+                let response = refresh_token(client, config, &refresh).await?;
+                *token = CachedToken::from_response(response, Some(&token));
+
+                if let Some(cache) = &self.cache {
+                    cache.store(&token)?;
+                }
+            }
+        }
+
+        Ok(token.access_token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Request, Response};
+
+    /// A local stand-in authorization server, which serves each of the `responses` (a status and JSON body) in turn.
+    fn authority(responses: Vec<(u16, &str)>) -> (String, thread::JoinHandle<Vec<Request>>) {
+        stub::serve(responses.into_iter().map(|(status, body)| Response::json(status, body)).collect())
+    }
+
+    fn config(authority: String) -> OAuthConfig {
+        OAuthConfig { authority, ..OAuthConfig::new("client") }
+    }
+
+    const DEVICE_CODE: &str = r#"{"device_code":"device","user_code":"USER","verification_uri":"https://microsoft.com/devicelogin","expires_in":900,"interval":0}"#;
+
+    const TOKEN: &str = r#"{"expires_in":3600,"access_token":"access","refresh_token":"refresh","scope":"User.Read Tasks.Read"}"#;
+
+    #[test]
+    fn polls_until_signed_in() {
+        let (url, requests) = authority(vec![
+            (200, DEVICE_CODE),
+            (400, r#"{"error":"authorization_pending"}"#),
+            (400, r#"{"error":"slow_down"}"#),
+            (200, TOKEN),
+        ]);
+        let config = config(url);
+        let client = reqwest::blocking::Client::new();
+
+        let started = Instant::now();
+        let token = DeviceCodeFlow::new(&client, &config).sign_in(|code| assert_eq!(code.user_code, "USER")).unwrap();
+
+        assert_eq!(token.access_token, "access");
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+
+        // Being told to slow down adds to the interval, which started at nothing
+        assert!(started.elapsed() >= Duration::from_secs(DEFAULT_POLL_INTERVAL));
+
+        let requests = requests.join().unwrap();
+        assert_eq!(requests[0].path, "/common/oauth2/v2.0/devicecode");
+        assert!(requests[0].body.starts_with("client_id=client&scope=User.Read"));
+        for request in &requests[1..] {
+            assert_eq!(request.path, "/common/oauth2/v2.0/token");
+            assert!(request.body.starts_with("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code"));
+            assert!(request.body.ends_with("device_code=device"));
+        }
+    }
+
+    #[test]
+    fn fails_once_the_code_has_expired() {
+        let (url, requests) = authority(vec![
+            (200, DEVICE_CODE),
+            (400, r#"{"error":"authorization_pending"}"#),
+            (400, r#"{"error":"expired_token","error_description":"The code has expired."}"#),
+        ]);
+        let config = config(url);
+        let client = reqwest::blocking::Client::new();
+
+        match DeviceCodeFlow::new(&client, &config).sign_in(|_| {}) {
+            Err(Error::AuthError(e)) => assert_eq!(e.error, "expired_token"),
+            other => panic!("expected the sign in to fail, got: {:?}", other.map(|token| token.access_token)),
+        }
+
+        assert_eq!(requests.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn renews_expiring_tokens() {
+        let (url, requests) = authority(vec![
+            (200, r#"{"expires_in":3600,"access_token":"renewed"}"#),
+        ]);
+        let token = CachedToken {
+            expires_at: 0,
+            refresh_token: Some("refresh".to_string()),
+            scopes: vec!["Tasks.Read".to_string()],
+            ..CachedToken::from_access_token("expired".to_string())
+        };
+        let session = Session::new(token, reqwest::Client::new(), config(url), None);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        assert_eq!(runtime.block_on(session.access_token()).unwrap(), "renewed");
+
+        // The renewed token is kept, along with the refresh token and scopes it didn't replace
+        assert_eq!(runtime.block_on(session.access_token()).unwrap(), "renewed");
+        let token = runtime.block_on(session.token.lock());
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(token.scopes, vec!["Tasks.Read"]);
+
+        let requests = requests.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/common/oauth2/v2.0/token");
+        assert!(requests[0].body.starts_with("grant_type=refresh_token&client_id=client&refresh_token=refresh"));
+    }
+}
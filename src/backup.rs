@@ -0,0 +1,88 @@
+//! A backup of a user's lists and tasks, which each of the exporters write, and which may be restored.
+
+use crate::api::DateTimeOffset;
+use crate::api::tasks::{TodoTask, TodoTaskList};
+use crate::api::user::User;
+
+/// The version of the backup document format written by this program. 
+/// This should be incremented whenever a change is made which older versions wouldn't be able to read. 
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// A backup of one or more of the user's task lists, and all of the tasks within them. 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    /// The version of the format the backup was written in. 
+    pub version: u32,
+
+    /// When the backup was taken. 
+    pub exported_at: DateTimeOffset,
+
+    /// The user whose lists were exported. 
+    pub user: Option<UserSummary>,
+
+    /// The lists which were exported, in the order they were returned by Graph.
+    pub lists: Vec<ListBackup>,
+
+    /// The delta link from which changes to the user's lists can be fetched, for incremental backups. 
+    /// This is only recorded when every list was exported. 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lists_delta_link: Option<String>,
+}
+
+/// The identifying properties of the `User` whose lists were exported. 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSummary {
+    pub id: String,
+
+    pub display_name: String,
+
+    pub user_principal_name: String,
+
+    pub mail: Option<String>,
+}
+
+/// A single task list, and its tasks. 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ListBackup {
+    /// The list itself, including whether it's owned by, or shared with, the user. 
+    pub list: TodoTaskList,
+
+    /// Every task within the list. 
+    pub tasks: Vec<TodoTask>,
+
+    /// The delta link from which changes to the list's tasks can be fetched, for incremental backups. 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_link: Option<String>,
+}
+
+impl Backup {
+    /// Start a new, empty, backup of the given `user`'s lists, taken now. 
+    pub fn new(user: Option<UserSummary>) -> Self {
+        Self {
+            version: BACKUP_FORMAT_VERSION,
+            exported_at: chrono::Utc::now().into(),
+            user,
+            lists: Vec::new(),
+            lists_delta_link: None,
+        }
+    }
+
+    /// The total number of tasks across all lists. 
+    pub fn task_count(&self) -> usize {
+        self.lists.iter().map(|list| list.tasks.len()).sum()
+    }
+}
+
+impl From<&User> for UserSummary {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id.clone(),
+            display_name: user.display_name.clone(),
+            user_principal_name: user.user_principal_name.clone(),
+            mail: user.mail.clone(),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! JSON batching; combining many requests into a single `POST /$batch`.
+//!
+//! Exporting the attachments of each task, or each task changed since a delta link, would otherwise
+//! need a round trip per task. Graph accepts up to 20 requests in a batch, and responds to each
+//! individually, so a sub-request may be throttled even when the batch itself succeeds.
+//!
+//! See: <https://docs.microsoft.com/en-us/graph/json-batching>
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+use crate::client::GraphClient;
+use crate::error::{Error, GraphError, Result};
+use crate::retry::{self, RetriesExhausted};
+
+/// The most requests Graph accepts in a single batch.
+pub const MAX_BATCH_SIZE: usize = 20;
+
+#[derive(Serialize, Debug)]
+struct BatchRequest<'a> {
+    requests: Vec<SubRequest<'a>>,
+}
+
+/// One of the requests within a batch.
+/// Its `url` is relative to the version of the API, eg: `/me/todo/lists`.
+#[derive(Serialize, Debug)]
+struct SubRequest<'a> {
+    id: String,
+    method: &'static str,
+    url: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResponse {
+    responses: Vec<SubResponse>,
+}
+
+/// The response to one of the requests within a batch, correlated to it by `id`.
+#[derive(Deserialize, Debug)]
+struct SubResponse {
+    id: String,
+
+    status: u16,
+
+    #[serde(default)]
+    headers: HashMap<String, String>,
+
+    #[serde(default)]
+    body: serde_json::Value,
+}
+
+impl SubResponse {
+    fn header(&self, name: &str) -> Option<String> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// The headers of the response, as they'd be given by `reqwest`. Any which aren't valid are left out.
+    fn header_map(&self) -> HeaderMap {
+        self.headers.iter()
+            .filter_map(|(name, value)| Some((HeaderName::from_bytes(name.as_bytes()).ok()?, HeaderValue::from_str(value).ok()?)))
+            .collect()
+    }
+
+    /// Deserialize the body of a successful response, or turn an unsuccessful one into a `GraphError`.
+    fn into_result<T: DeserializeOwned>(self, status: StatusCode) -> Result<T> {
+        if status.is_success() {
+            return Ok(serde_json::from_value(self.body).map_err(io::Error::from)?);
+        }
+
+        let body = serde_json::to_vec(&self.body).map_err(io::Error::from)?;
+
+        Err(Error::GraphError(Box::new(GraphError::from_response_parts(status, self.header("request-id"), self.header("client-request-id"), &body))))
+    }
+}
+
+/// GET each of the `requests`, a key (such as the ID of a task) and the path to fetch for it, in batches,
+/// returning the result of each by its key.
+///
+/// A failed request doesn't fail the others; its error is returned against its key, and only a failure of
+/// a whole batch is returned as an error. Requests which are throttled, or fail with a transient error,
+/// are re-queued into a later batch, after waiting as long as Graph asked, according to the client's `RetryPolicy`.
+pub fn get_each<T: DeserializeOwned>(client: &GraphClient, requests: Vec<(String, String)>) -> Result<HashMap<String, Result<T>>> {
+    let policy = client.retry_policy();
+    let url = client.url("/$batch");
+
+    let mut results: Vec<Option<Result<T>>> = requests.iter().map(|_| None).collect();
+    let mut attempts = vec![0; requests.len()];
+    let mut waited = vec![Duration::from_secs(0); requests.len()];
+    let mut queue: VecDeque<usize> = (0..requests.len()).collect();
+
+    while !queue.is_empty() {
+        let batch: Vec<usize> = queue.drain(..queue.len().min(MAX_BATCH_SIZE)).collect();
+
+        // The index of each request is used as its ID, as a key may be too long to be used as one.
+        let body = BatchRequest {
+            requests: batch.iter().map(|&i| SubRequest { id: i.to_string(), method: "GET", url: &requests[i].1 }).collect(),
+        };
+
+        // The batch only contains GET requests, so it may be retried like one.
+        let response: BatchResponse = client.post_json_idempotent(&url, &body)?;
+
+        let mut responses: HashMap<usize, SubResponse> = response.responses.into_iter()
+            .filter_map(|response| Some((response.id.parse().ok()?, response)))
+            .collect();
+
+        let mut throttled = Duration::from_secs(0);
+        let mut delay = Duration::from_secs(0);
+
+        for i in batch {
+            // Graph should respond to every request, but one without a response can simply be tried again.
+            let (status, response) = match responses.remove(&i) {
+                Some(response) => (StatusCode::from_u16(response.status).ok(), Some(response)),
+                None => (None, None),
+            };
+
+            let response = match (status, response) {
+                (Some(status), Some(response)) if !retry::is_retryable_status(status, true) => {
+                    results[i] = Some(response.into_result(status));
+                    continue;
+                },
+                (_, response) => response,
+            };
+
+            let retry_delay = policy.delay(attempts[i], response.as_ref().map(SubResponse::header_map).as_ref());
+
+            if attempts[i] >= policy.max_retries || waited[i] + retry_delay > policy.max_total_wait {
+                results[i] = Some(Err(Error::RetriesExhausted(RetriesExhausted { attempts: attempts[i] + 1, last_status: status, last_error: None })));
+                continue;
+            }
+
+            attempts[i] += 1;
+            waited[i] += retry_delay;
+
+            if status.is_some_and(retry::is_throttled_status) {
+                throttled = throttled.max(retry_delay);
+            } else {
+                delay = delay.max(retry_delay);
+            }
+
+            queue.push_back(i);
+        }
+
+        // Throttling applies to the whole app, so there's no use sending anything else before the delay is up,
+        // whereas a transient failure only holds back the next batch.
+        if throttled > Duration::from_secs(0) {
+            client.throttle(throttled);
+        }
+
+        thread::sleep(delay);
+    }
+
+    Ok(requests.into_iter()
+        .zip(results)
+        .filter_map(|((key, _), result)| Some((key, result?)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::stub::{self, Response};
+
+    /// The response to a batch, with each sub-response given as its ID, status and body.
+    fn batch(responses: &[(&str, u16, serde_json::Value)]) -> Response {
+        let responses: Vec<serde_json::Value> = responses.iter()
+            .map(|(id, status, body)| json!({ "id": id, "status": status, "body": body }))
+            .collect();
+
+        Response::json(200, json!({ "responses": responses }).to_string())
+    }
+
+    fn keyed(keys: &[&str]) -> Vec<(String, String)> {
+        keys.iter().map(|key| (key.to_string(), format!("/items/{}", key))).collect()
+    }
+
+    /// The IDs and URLs of the sub-requests of a batch request.
+    fn sub_requests(request: &stub::Request) -> Vec<(String, String)> {
+        request.json()["requests"].as_array().unwrap().iter()
+            .map(|request| (request["id"].as_str().unwrap().to_string(), request["url"].as_str().unwrap().to_string()))
+            .collect()
+    }
+
+    fn value(result: &Result<serde_json::Value>) -> &serde_json::Value {
+        result.as_ref().expect("the request should have succeeded")
+    }
+
+    #[test]
+    fn responses_are_correlated_by_id() {
+        let (url, requests) = stub::serve(vec![batch(&[
+            ("2", 200, json!({ "key": "c" })),
+            ("0", 200, json!({ "key": "a" })),
+            ("1", 404, json!({ "error": { "code": "ErrorItemNotFound", "message": "Not found" } })),
+        ])]);
+        let client = stub::client(&url);
+
+        let mut results = get_each::<serde_json::Value>(&client, keyed(&["a", "b", "c"])).unwrap();
+
+        assert_eq!(value(&results["a"])["key"], "a");
+        assert_eq!(value(&results["c"])["key"], "c");
+        match results.remove("b") {
+            Some(Err(Error::GraphError(e))) => assert_eq!(e.status, StatusCode::NOT_FOUND),
+            other => panic!("expected the request to fail, got: {:?}", other.map(|result| result.ok())),
+        }
+
+        let requests = requests.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!((requests[0].method.as_str(), requests[0].path.as_str()), ("POST", "/$batch"));
+        assert_eq!(sub_requests(&requests[0]), vec![
+            ("0".to_string(), "/items/a".to_string()),
+            ("1".to_string(), "/items/b".to_string()),
+            ("2".to_string(), "/items/c".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn throttled_and_missing_responses_are_retried() {
+        let (url, requests) = stub::serve(vec![
+            batch(&[
+                ("0", 200, json!({ "key": "a" })),
+                ("1", 429, json!({ "error": { "code": "TooManyRequests", "message": "Slow down" } })),
+                ("2", 502, json!(null)),
+            ]),
+            batch(&[
+                ("1", 200, json!({ "key": "b" })),
+                ("2", 200, json!({ "key": "c" })),
+            ]),
+            batch(&[
+                ("3", 200, json!({ "key": "d" })),
+            ]),
+        ]);
+        let client = stub::client(&url);
+
+        // There's no response to "d" in the first batch.
+        let results = get_each::<serde_json::Value>(&client, keyed(&["a", "b", "c", "d"])).unwrap();
+
+        for key in &["a", "b", "c", "d"] {
+            assert_eq!(value(&results[*key])["key"], *key);
+        }
+
+        let ids = |request| sub_requests(request).into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        let requests = requests.join().unwrap();
+        assert_eq!(ids(&requests[0]), vec!["0", "1", "2", "3"]);
+        assert_eq!(ids(&requests[1]), vec!["1", "2", "3"]);
+        assert_eq!(ids(&requests[2]), vec!["3"]);
+    }
+
+    #[test]
+    fn throttled_batches_are_retried() {
+        let (url, requests) = stub::serve(vec![
+            Response::json(429, r#"{"error":{"code":"TooManyRequests","message":"Slow down"}}"#).header("Retry-After", "0"),
+            batch(&[("0", 200, json!({ "key": "a" }))]),
+        ]);
+        let client = stub::client(&url);
+
+        let results = get_each::<serde_json::Value>(&client, keyed(&["a"])).unwrap();
+
+        assert_eq!(value(&results["a"])["key"], "a");
+        assert_eq!(requests.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn requests_give_up_once_retries_are_exhausted() {
+        let unavailable = || batch(&[("0", 503, json!(null)), ("1", 200, json!({ "key": "b" }))]);
+        let (url, requests) = stub::serve(vec![
+            unavailable(),
+            batch(&[("0", 503, json!(null))]),
+            batch(&[("0", 503, json!(null))]),
+        ]);
+        let client = stub::client(&url);
+
+        let results = get_each::<serde_json::Value>(&client, keyed(&["a", "b"])).unwrap();
+
+        assert_eq!(value(&results["b"])["key"], "b");
+        match &results["a"] {
+            Err(Error::RetriesExhausted(e)) => {
+                assert_eq!(e.attempts, 3);
+                assert_eq!(e.last_status, Some(StatusCode::SERVICE_UNAVAILABLE));
+            },
+            other => panic!("expected the request to give up, got: {:?}", other.as_ref().ok()),
+        }
+
+        assert_eq!(requests.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn requests_are_split_into_batches() {
+        let keys: Vec<String> = (0..MAX_BATCH_SIZE + 5).map(|i| i.to_string()).collect();
+
+        let first: Vec<(&str, u16, serde_json::Value)> = keys[..MAX_BATCH_SIZE].iter().map(|key| (key.as_str(), 200, json!(key))).collect();
+        let second: Vec<(&str, u16, serde_json::Value)> = keys[MAX_BATCH_SIZE..].iter().map(|key| (key.as_str(), 200, json!(key))).collect();
+        let (url, requests) = stub::serve(vec![batch(&first), batch(&second)]);
+        let client = stub::client(&url);
+
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let results = get_each::<String>(&client, keyed(&keys)).unwrap();
+
+        assert_eq!(results.len(), keys.len());
+        for key in &keys {
+            assert_eq!(results[*key].as_ref().unwrap(), key);
+        }
+
+        let requests = requests.join().unwrap();
+        assert_eq!(sub_requests(&requests[0]).len(), MAX_BATCH_SIZE);
+        assert_eq!(sub_requests(&requests[1]).len(), 5);
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use microsoft_todo_export::auth;
+use microsoft_todo_export::client;
+use microsoft_todo_export::export::csv;
+use microsoft_todo_export::retry::RetryPolicy;
+
+/// Export tasks from Microsoft To Do.
+///
+/// Run without a command to pick a list to export interactively.
+#[derive(Parser, Debug)]
+#[command(name = "microsoft-todo-export", version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub auth: AuthArgs,
+
+    /// The Microsoft Graph endpoint to send requests to.
+    #[arg(long, env = "TODO_EXPORT_GRAPH_URL", default_value = client::GRAPH_BASE_URI, global = true)]
+    pub graph_url: String,
+
+    /// The maximum number of times a throttled, or failed, request is retried.
+    #[arg(long, env = "TODO_EXPORT_MAX_RETRIES", default_value_t = RetryPolicy::default().max_retries, global = true)]
+    pub max_retries: u32,
+
+    /// The maximum total number of seconds to wait between retries of a single request.
+    #[arg(long, value_name = "SECONDS", env = "TODO_EXPORT_MAX_RETRY_WAIT", default_value_t = RetryPolicy::default().max_total_wait.as_secs(), global = true)]
+    pub max_retry_wait: u64,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// How to sign in to Microsoft Graph.
+///
+/// If neither a token nor a client ID is given, the user is asked to paste a token.
+#[derive(Args, Debug)]
+pub struct AuthArgs {
+    /// An access token to use instead of signing in.
+    #[arg(long, env = "TODO_EXPORT_TOKEN", hide_env_values = true, global = true)]
+    pub token: Option<String>,
+
+    /// Read the access token to use from a file.
+    #[arg(long, value_name = "PATH", conflicts_with = "token", global = true)]
+    pub token_file: Option<PathBuf>,
+
+    /// The Application (client) ID to sign in with, using a device code.
+    #[arg(long, env = "TODO_EXPORT_CLIENT_ID", global = true)]
+    pub client_id: Option<String>,
+
+    /// The tenant to sign in to.
+    #[arg(long, env = "TODO_EXPORT_TENANT", default_value = auth::DEFAULT_TENANT, global = true)]
+    pub tenant: String,
+
+    /// The scopes to request when signing in, separated by spaces.
+    #[arg(long, env = "TODO_EXPORT_SCOPES", value_delimiter = ' ', default_values_t = auth::DEFAULT_SCOPES.iter().map(|s| s.to_string()), global = true)]
+    pub scopes: Vec<String>,
+
+    /// The authorization server to sign in with.
+    #[arg(long, env = "TODO_EXPORT_AUTHORITY", default_value = auth::DEFAULT_AUTHORITY, global = true)]
+    pub authority: String,
+
+    /// The name under which the signed in user's tokens are cached.
+    #[arg(long, env = "TODO_EXPORT_ACCOUNT", default_value = "default", global = true)]
+    pub account: String,
+}
+
+impl Cli {
+    /// How throttled, or failed, requests should be retried.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            max_total_wait: Duration::from_secs(self.max_retry_wait),
+            ..RetryPolicy::default()
+        }
+    }
+}
+
+impl AuthArgs {
+    /// The device code sign-in configuration, if a client ID has been given.
+    pub fn oauth_config(&self) -> Option<auth::OAuthConfig> {
+        let client_id = self.client_id.as_deref().map(str::trim).filter(|id| !id.is_empty())?;
+
+        let mut config = auth::OAuthConfig::new(client_id);
+        config.tenant = self.tenant.clone();
+        config.scopes = self.scopes.iter().filter(|s| !s.is_empty()).cloned().collect();
+        config.authority = self.authority.clone();
+
+        Some(config)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Show the signed in user.
+    Whoami,
+
+    /// Show the user's task lists.
+    Lists,
+
+    /// Export the tasks of one or more lists.
+    Export(ExportArgs),
+
+    /// Recreate the lists and tasks of a JSON backup in the signed in account.
+    ///
+    /// This requires the `Tasks.ReadWrite` scope.
+    Restore(RestoreArgs),
+}
+
+/// The number of lists which are exported at once, by default.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// The name or ID of a list to export. May be given more than once.
+    ///
+    /// If no lists are given, and `--all` isn't set, the list is picked interactively.
+    #[arg(short, long = "list", value_name = "NAME_OR_ID")]
+    pub lists: Vec<String>,
+
+    /// Export every list.
+    #[arg(long, conflicts_with = "lists")]
+    pub all: bool,
+
+    /// The file to write the export to. Defaults to standard output.
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// The format of the export.
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    pub format: Format,
+
+    /// Download each task's attachments into this directory, with each file named by the SHA-256 hash of its content.
+    ///
+    /// The name, type, size and hash of each attachment is recorded against its task in JSON exports.
+    #[arg(long, value_name = "DIRECTORY")]
+    pub attachments: Option<PathBuf>,
+
+    /// Write each list to its own Markdown file, within the directory given by `--output`.
+    #[arg(long, requires = "output")]
+    pub file_per_list: bool,
+
+    /// The columns of a CSV export, separated by commas.
+    #[arg(long, value_delimiter = ',', default_values_t = csv::Column::DEFAULT.to_vec())]
+    pub columns: Vec<csv::Column>,
+
+    /// Update the JSON backup at `--output`, fetching only the lists and tasks which have changed since it was taken.
+    ///
+    /// If the file doesn't exist yet, everything is fetched, and the backup written can be updated next time.
+    #[arg(long, requires = "output")]
+    pub incremental: bool,
+
+    /// The number of lists to fetch at once.
+    ///
+    /// However many lists are fetched at once, they're written in the same order,
+    /// and if Graph throttles any request, every request waits.
+    #[arg(short = 'j', long, env = "TODO_EXPORT_CONCURRENCY", default_value_t = DEFAULT_CONCURRENCY, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=64))]
+    pub concurrency: usize,
+}
+
+/// The arguments used when no command is given; the list is picked interactively, and exported as JSON.
+impl Default for ExportArgs {
+    fn default() -> Self {
+        Self {
+            lists: Vec::new(),
+            all: false,
+            output: None,
+            format: Format::default(),
+            attachments: None,
+            file_per_list: false,
+            columns: csv::Column::DEFAULT.to_vec(),
+            incremental: false,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+/// The formats tasks may be exported in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// A versioned JSON backup, containing every property of every list and task.
+    #[default]
+    Json,
+
+    /// One row per task, with the columns chosen by `--columns`.
+    Csv,
+
+    /// An iCalendar document, with a VTODO for each task.
+    Ics,
+
+    /// One task per line, in the todo.txt format.
+    TodoTxt,
+
+    /// A checklist of each list's tasks, with their due dates, importance and notes.
+    Markdown,
+
+    /// The title of each task, one per line.
+    Text,
+}
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    /// The backup to restore.
+    #[arg(value_name = "PATH")]
+    pub input: PathBuf,
+
+    /// The format of the backup.
+    #[arg(short, long, value_enum, default_value_t = InputFormat::Json)]
+    pub format: InputFormat,
+
+    /// Write the IDs of the created lists and tasks, keyed by their IDs in the backup, to a file.
+    #[arg(long, value_name = "PATH")]
+    pub id_map: Option<PathBuf>,
+}
+
+/// The formats a backup may be restored from.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// A JSON backup, as written by `export --format json`.
+    Json,
+
+    /// A todo.txt file. Each task is restored into the list named by its first project.
+    TodoTxt,
+}
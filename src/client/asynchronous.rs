@@ -0,0 +1,191 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::tasks::{TodoTask, TodoTaskList};
+use crate::api::user::User;
+use crate::auth::Session;
+use crate::error::{Error, GraphError, Result};
+use crate::reader::CollectionStream;
+use crate::retry::{self, RetriesExhausted, RetryPolicy};
+
+/// Turn an unsuccessful `response` into a `GraphError`.
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+
+    let status = response.status();
+    let header = |name: &str| response.headers().get(name).and_then(|value| value.to_str().ok()).map(String::from);
+    let request_id = header("request-id");
+    let client_request_id = header("client-request-id");
+
+    let body = response.bytes().await.unwrap_or_default();
+
+    Err(Error::GraphError(Box::new(GraphError::from_response_parts(status, request_id, client_request_id, &body))))
+}
+
+/// Sends authenticated requests to the Microsoft Graph API, asynchronously, for use within a `tokio` runtime.
+///
+/// The `Session`'s access token is renewed before any request is sent with an expired token,
+/// requests which are throttled, or fail with a transient error, are retried according to the `RetryPolicy`,
+/// and any other unsuccessful response is returned as an `Error::GraphError`.
+///
+/// A client may be shared between tasks. Graph throttles the app as a whole, rather than each request,
+/// so when any request is throttled, no request is sent by any task until the delay Graph asked for has passed.
+pub struct AsyncGraphClient {
+    /// The `reqwest` client used to send requests.
+    http: reqwest::Client,
+
+    /// The signed in user's `Session`.
+    session: Session,
+
+    /// The URL which API paths are relative to.
+    base_url: String,
+
+    /// How failed requests are retried.
+    retry_policy: RetryPolicy,
+
+    /// When requests may be sent again, after being throttled.
+    throttled_until: Mutex<Option<Instant>>,
+}
+
+impl AsyncGraphClient {
+    /// Create a client which sends requests to the Graph endpoint at `base_url`;
+    /// normally `GRAPH_BASE_URI`, but this may be a national cloud deployment instead.
+    pub fn new<S: Into<String>>(http: reqwest::Client, session: Session, base_url: S) -> Self {
+        Self { http, session, base_url: base_url.into(), retry_policy: RetryPolicy::default(), throttled_until: Mutex::new(None) }
+    }
+
+    /// Use the given `policy` to retry failed requests.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// How failed requests are retried.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Hold back every request, from every task, until the given `delay` has passed.
+    pub fn throttle(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut throttled_until = self.throttled_until.lock().expect("Throttle lock was poisoned");
+
+        *throttled_until = Some(throttled_until.map_or(until, |current| current.max(until)));
+    }
+
+    /// Wait until requests are no longer being throttled.
+    async fn wait_for_throttle(&self) {
+        let until = *self.throttled_until.lock().expect("Throttle lock was poisoned");
+
+        if let Some(delay) = until.and_then(|until| until.checked_duration_since(Instant::now())) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Build the full URL of the given Graph API `path` (eg: `/me`).
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Fetch the signed in `User`.
+    ///
+    /// See: <https://docs.microsoft.com/en-us/graph/api/user-get?view=graph-rest-1.0>
+    pub async fn me(&self) -> Result<User> {
+        self.get_json(&self.url("/me")).await
+    }
+
+    /// Read all of the user's task lists, with their extensions.
+    ///
+    /// See: <https://docs.microsoft.com/en-us/graph/api/todo-list-lists?view=graph-rest-1.0>
+    pub fn lists(&self) -> CollectionStream<'_, TodoTaskList> {
+        CollectionStream::new(self, self.url(&format!("/me/todo/lists?{}", super::LIST_EXPANSIONS)))
+    }
+
+    /// Read all of the tasks in the list with the given ID, with their checklist items, linked resources and extensions.
+    ///
+    /// See: <https://docs.microsoft.com/en-us/graph/api/todotasklist-list-tasks?view=graph-rest-1.0>
+    pub fn tasks(&self, list_id: &str) -> CollectionStream<'_, TodoTask> {
+        CollectionStream::new(self, self.url(&format!("/me/todo/lists/{}/tasks?{}", super::encode_segment(list_id), super::TASK_EXPANSIONS)))
+    }
+
+    /// Send a GET request to the given `url`.
+    pub async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        self.send(true, |http| http.get(url)).await
+    }
+
+    /// Send a GET request to the given `url`, and deserialize the JSON response.
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        Ok(self.get(url).await?.json().await?)
+    }
+
+    /// Send a POST request to the given `url`, with the JSON encoded `body`.
+    /// It's only retried if it was throttled, as it may have been carried out despite any other failure.
+    pub async fn post<B: Serialize + ?Sized>(&self, url: &str, body: &B) -> Result<reqwest::Response> {
+        self.send(false, |http| http.post(url).json(body)).await
+    }
+
+    /// Send a POST request to the given `url`, with the JSON encoded `body`, and deserialize the JSON response.
+    pub async fn post_json<B: Serialize + ?Sized, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T> {
+        Ok(self.post(url, body).await?.json().await?)
+    }
+
+    /// Send a POST request which only reads, such as a `$batch` of GET requests, to the given `url`,
+    /// with the JSON encoded `body`, and deserialize the JSON response.
+    /// Unlike other POST requests, it's retried after a transient error, in the same way as a GET request.
+    pub async fn post_json_idempotent<B: Serialize + ?Sized, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T> {
+        Ok(self.send(true, |http| http.post(url).json(body)).await?.json().await?)
+    }
+
+    /// Send a PATCH request to the given `url`, with the JSON encoded `body`.
+    /// It's only retried if it was throttled, as it may have been carried out despite any other failure.
+    pub async fn patch<B: Serialize + ?Sized>(&self, url: &str, body: &B) -> Result<reqwest::Response> {
+        self.send(false, |http| http.patch(url).json(body)).await
+    }
+
+    /// Send the request created by `build`, retrying it if it's throttled or, if it's `idempotent`, fails with a transient error.
+    /// The request is rebuilt for each attempt, so that it's sent with a current access token.
+    async fn send<F>(&self, idempotent: bool, build: F) -> Result<reqwest::Response>
+        where F: Fn(&reqwest::Client) -> reqwest::RequestBuilder
+    {
+        let policy = &self.retry_policy;
+        let mut waited = Duration::from_secs(0);
+        let mut attempt = 0;
+
+        loop {
+            self.wait_for_throttle().await;
+
+            let result = build(&self.http)
+                .bearer_auth(self.session.access_token().await?)
+                .send()
+                .await;
+
+            let delay = match &result {
+                Ok(response) if retry::is_retryable_status(response.status(), idempotent) => policy.delay(attempt, Some(response.headers())),
+                Err(e) if retry::is_retryable_error(e, idempotent) => policy.delay(attempt, None),
+                _ => return check_status(result?).await,
+            };
+
+            if attempt >= policy.max_retries || waited + delay > policy.max_total_wait {
+                let (last_status, last_error) = match result {
+                    Ok(response) => (Some(response.status()), None),
+                    Err(e) => (None, Some(e)),
+                };
+
+                return Err(Error::RetriesExhausted(RetriesExhausted { attempts: attempt + 1, last_status, last_error }));
+            }
+
+            match &result {
+                Ok(response) if retry::is_throttled_status(response.status()) => self.throttle(delay),
+                _ => tokio::time::sleep(delay).await,
+            }
+
+            waited += delay;
+            attempt += 1;
+        }
+    }
+}
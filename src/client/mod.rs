@@ -0,0 +1,148 @@
+//! Clients which send authenticated requests to the Graph API; a blocking `GraphClient`, and an `AsyncGraphClient`.
+
+use std::future::Future;
+use std::io::Write;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::tasks::{TodoTask, TodoTaskList};
+use crate::api::user::User;
+use crate::auth::Session;
+use crate::error::Result;
+use crate::reader::CollectionReader;
+use crate::retry::RetryPolicy;
+
+mod asynchronous;
+
+pub use asynchronous::AsyncGraphClient;
+
+pub const GRAPH_BASE_URI: &str = "https://graph.microsoft.com/beta";
+
+/// The related resources of each list which are fetched alongside it.
+pub const LIST_EXPANSIONS: &str = "$expand=extensions";
+
+/// The related resources of each task which are fetched alongside it, rather than needing requests per task.
+pub const TASK_EXPANSIONS: &str = "$expand=checklistItems,linkedResources,extensions";
+
+/// Percent-encode an ID for use as a segment of a URL's path. 
+/// Some IDs, such as those of attachments, are base64 encoded, and so may contain `/` and `+`. 
+pub fn encode_segment(segment: &str) -> String {
+    segment.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'=' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Sends authenticated requests to the Microsoft Graph API, blocking until each is complete.
+///
+/// This is a thin wrapper around an `AsyncGraphClient`, which runs each request on a runtime of its own,
+/// so it mustn't be used from within another `tokio` runtime; use the `AsyncGraphClient` there instead.
+/// It may be shared between threads, which share its throttling, as tasks sharing an `AsyncGraphClient` do.
+pub struct GraphClient {
+    /// The client which sends the requests.
+    client: AsyncGraphClient,
+
+    /// The runtime each request is run on.
+    runtime: tokio::runtime::Runtime,
+}
+
+impl GraphClient {
+    /// Create a client which sends requests to the Graph endpoint at `base_url`;
+    /// normally `GRAPH_BASE_URI`, but this may be a national cloud deployment instead.
+    pub fn new<S: Into<String>>(http: reqwest::Client, session: Session, base_url: S) -> Result<Self> {
+        // A single worker drives the connections; each request's future runs on the thread which is waiting for it.
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+
+        Ok(Self { client: AsyncGraphClient::new(http, session, base_url), runtime })
+    }
+
+    /// Use the given `policy` to retry failed requests.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.client = self.client.with_retry_policy(policy);
+        self
+    }
+
+    /// How failed requests are retried.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        self.client.retry_policy()
+    }
+
+    /// Hold back every request, from every thread, until the given `delay` has passed.
+    pub fn throttle(&self, delay: Duration) {
+        self.client.throttle(delay)
+    }
+
+    /// Build the full URL of the given Graph API `path` (eg: `/me`).
+    pub fn url(&self, path: &str) -> String {
+        self.client.url(path)
+    }
+
+    /// The client which sends the requests, for use within the client's runtime.
+    pub fn async_client(&self) -> &AsyncGraphClient {
+        &self.client
+    }
+
+    /// Run the `future` to completion, on the client's runtime, blocking until it's complete.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Fetch the signed in `User`.
+    pub fn me(&self) -> Result<User> {
+        self.block_on(self.client.me())
+    }
+
+    /// Read all of the user's task lists, with their extensions.
+    pub fn lists(&self) -> CollectionReader<'_, TodoTaskList> {
+        CollectionReader::from_stream(self, self.client.lists())
+    }
+
+    /// Read all of the tasks in the list with the given ID, with their checklist items, linked resources and extensions.
+    pub fn tasks(&self, list_id: &str) -> CollectionReader<'_, TodoTask> {
+        CollectionReader::from_stream(self, self.client.tasks(list_id))
+    }
+
+    /// Send a GET request to the given `url`, and write the body of the response to the `output`, as it's received.
+    pub fn download<W: Write + ?Sized>(&self, url: &str, output: &mut W) -> Result<()> {
+        self.block_on(async {
+            let mut response = self.client.get(url).await?;
+
+            while let Some(chunk) = response.chunk().await? {
+                output.write_all(&chunk)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Send a POST request to the given `url`, with the JSON encoded `body`.
+    pub fn post<B: Serialize + ?Sized>(&self, url: &str, body: &B) -> Result<()> {
+        self.block_on(self.client.post(url, body))?;
+        Ok(())
+    }
+
+    /// Send a POST request to the given `url`, with the JSON encoded `body`, and deserialize the JSON response.
+    pub fn post_json<B: Serialize + ?Sized, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T> {
+        self.block_on(self.client.post_json(url, body))
+    }
+
+    /// Send a POST request which only reads, such as a `$batch` of GET requests, to the given `url`,
+    /// with the JSON encoded `body`, and deserialize the JSON response.
+    /// Unlike other POST requests, it's retried after a transient error, in the same way as a GET request.
+    pub fn post_json_idempotent<B: Serialize + ?Sized, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T> {
+        self.block_on(self.client.post_json_idempotent(url, body))
+    }
+
+    /// Send a PATCH request to the given `url`, with the JSON encoded `body`.
+    pub fn patch<B: Serialize + ?Sized>(&self, url: &str, body: &B) -> Result<()> {
+        self.block_on(self.client.patch(url, body))?;
+        Ok(())
+    }
+}
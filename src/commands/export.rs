@@ -0,0 +1,304 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+
+use reqwest::StatusCode;
+
+use microsoft_todo_export::api::tasks::{TodoTask, TodoTaskList};
+use microsoft_todo_export::attachments::AttachmentStore;
+use microsoft_todo_export::backup::{Backup, ListBackup, UserSummary};
+use microsoft_todo_export::batch;
+use microsoft_todo_export::delta;
+use microsoft_todo_export::export;
+use microsoft_todo_export::client::{self, GraphClient};
+use microsoft_todo_export::error::{Error, Result};
+use microsoft_todo_export::pool;
+
+use crate::cli::{ExportArgs, Format};
+
+/// Export the tasks of the lists selected by the `args`. 
+pub fn export(client: &GraphClient, args: &ExportArgs) -> Result<()> {
+    if args.file_per_list && args.format != Format::Markdown {
+        return Err(Error::UsageError("`--file-per-list` is only supported by the markdown format".to_string()));
+    }
+
+    if args.incremental && args.format != Format::Json {
+        return Err(Error::UsageError("`--incremental` is only supported by the json format".to_string()));
+    }
+
+    // The previous backup is read before anything else, as it'll be overwritten by the new one.
+    let previous = match args.output.as_deref().filter(|_| args.incremental) {
+        Some(path) if path.exists() => Some(export::json::read(BufReader::new(File::open(path)?))?),
+        _ => None,
+    };
+
+    let me = client.me()?;
+
+    eprintln!("User: {} / {}", me.display_name, me.user_principal_name);
+
+    let mut backup = Backup::new(Some(UserSummary::from(&me)));
+
+    // Changes to the lists themselves can only be tracked when every list is exported.
+    let lists = if args.incremental && args.all {
+        let synced = sync_lists(client, previous.as_ref())?;
+        backup.lists_delta_link = synced.delta_link;
+        synced.items
+    } else {
+        select_lists(client.lists().collect::<Result<_>>()?, args)?
+    };
+
+    let attachments = match &args.attachments {
+        Some(directory) => Some(AttachmentStore::new(directory)?.with_progress(|attachment| {
+            eprintln!("Downloading attachment: {} ({} bytes)", attachment.name, attachment.size);
+        })),
+        None => None,
+    };
+    let mut backup = fetch_backup(client, backup, lists, args.concurrency, args.incremental, previous.as_ref(), attachments.as_ref())?;
+
+    eprintln!("Exported {} tasks from {} lists", backup.task_count(), backup.lists.len());
+
+    // Only some of the lists were fetched, so the rest of the previous backup is kept as it was.
+    if let (Some(previous), false) = (previous, args.all) {
+        backup = merge_untouched(backup, previous);
+    }
+
+    if args.file_per_list {
+        return write_file_per_list(&backup, args);
+    }
+
+    let mut output = super::open_output(args.output.as_deref())?;
+
+    match args.format {
+        Format::Json => export::json::write(&mut output, &backup)?,
+        Format::Csv => export::csv::write(&mut output, &backup, &args.columns)?,
+        Format::Ics => export::ical::write(&mut output, &backup)?,
+        Format::TodoTxt => export::todotxt::write(&mut output, &backup)?,
+        Format::Markdown => export::markdown::write(&mut output, &backup)?,
+        Format::Text => export::text::write(&mut output, &backup)?,
+    }
+
+    output.finish()?;
+
+    Ok(())
+}
+
+/// Write each list of the `backup` into its own file, within the output directory. 
+fn write_file_per_list(backup: &Backup, args: &ExportArgs) -> Result<()> {
+    let directory = args.output.as_deref()
+        .ok_or_else(|| Error::UsageError("`--file-per-list` requires an `--output` directory".to_string()))?;
+
+    fs::create_dir_all(directory)?;
+
+    for (list, file_name) in backup.lists.iter().zip(export::markdown::file_names(backup)) {
+        let path = directory.join(file_name);
+        eprintln!("Writing: {}", path.display());
+
+        let mut output = super::open_output(Some(&path))?;
+        export::markdown::write_list(&mut output, list)?;
+        output.finish()?;
+    }
+
+    Ok(())
+}
+
+/// Keep the lists of the `previous` backup which weren't fetched into the `backup`, in their previous order, 
+/// along with its lists' delta link; lists which weren't in the previous backup are added after them. 
+fn merge_untouched(mut backup: Backup, previous: Backup) -> Backup {
+    let mut lists = Vec::with_capacity(previous.lists.len());
+
+    for list in previous.lists {
+        match backup.lists.iter().position(|fetched| fetched.list.id == list.list.id) {
+            Some(i) => lists.push(backup.lists.remove(i)),
+            None => lists.push(list),
+        }
+    }
+
+    lists.append(&mut backup.lists);
+
+    backup.lists = lists;
+    backup.lists_delta_link = previous.lists_delta_link;
+    backup
+}
+
+/// Bring every one of the user's lists in the `previous` backup up to date, using its delta link. 
+fn sync_lists(client: &GraphClient, previous: Option<&Backup>) -> Result<delta::Synced<TodoTaskList>> {
+    let previous = previous.and_then(|backup| {
+        let lists = backup.lists.iter().map(|list| list.list.clone()).collect();
+        Some((lists, backup.lists_delta_link.as_deref()?))
+    });
+
+    if previous.is_some() {
+        eprintln!("Fetching changes to lists");
+    }
+
+    let synced = delta::sync(
+        client,
+        previous,
+        &client.url("/me/todo/lists/delta?$select=id"),
+        |list| &list.id,
+        || client.lists().collect(),
+        |ids| get_existing(client, ids, |id| format!("/me/todo/lists/{}?{}", client::encode_segment(id), client::LIST_EXPANSIONS)),
+    )?;
+
+    report_update("Lists", &synced.update);
+
+    Ok(synced)
+}
+
+/// Say how the collection named `label` was brought up to date, if it was by its delta link. 
+fn report_update(label: &str, update: &delta::Update) {
+    match update {
+        delta::Update::Changes(changes) => eprintln!("{}: {} changed, {} removed", label, changes.changed.len(), changes.removed.len()),
+        delta::Update::Expired => eprintln!("{}: The delta link had expired, so everything was fetched", label),
+        delta::Update::Everything => {},
+    }
+}
+
+/// Fetch every task within each of the `lists` into the `backup`, 
+/// and download their attachments, if an `AttachmentStore` is given. 
+/// 
+/// Up to `concurrency` lists are fetched at once, but the lists are added to the backup in their original order, 
+/// whichever finishes first. Once a list fails, no more are started. 
+fn fetch_backup(client: &GraphClient, mut backup: Backup, lists: Vec<TodoTaskList>, concurrency: usize, incremental: bool, previous: Option<&Backup>, attachments: Option<&AttachmentStore>) -> Result<Backup> {
+    let fetched = pool::try_map(&lists, concurrency, |list| fetch_list(client, list, incremental, previous, attachments))?;
+    backup.lists.extend(fetched);
+
+    Ok(backup)
+}
+
+/// Fetch every task within the `list`, and download their attachments, if an `AttachmentStore` is given. 
+/// 
+/// If the backup is `incremental`, a delta link is recorded for the list, and the list's tasks 
+/// in the `previous` backup are updated with only the changes since; only the attachments of changed tasks are downloaded. 
+fn fetch_list(client: &GraphClient, list: &TodoTaskList, incremental: bool, previous: Option<&Backup>, attachments: Option<&AttachmentStore>) -> Result<ListBackup> {
+    let fetch_all = || {
+        eprintln!("Fetching list: {} ({})", list.display_name, list.id);
+
+        let mut tasks = client.tasks(&list.id).collect::<Result<Vec<_>>>()?;
+
+        if let Some(store) = attachments {
+            store.download(client, &list.id, &mut tasks)?;
+        }
+
+        Ok(tasks)
+    };
+
+    let (tasks, delta_link) = if incremental {
+        let snapshot = previous.iter()
+            .flat_map(|previous| &previous.lists)
+            .find(|previous| previous.list.id == list.id)
+            .and_then(|previous| Some((previous.tasks.clone(), previous.delta_link.as_deref()?)));
+
+        if snapshot.is_some() {
+            eprintln!("Fetching changes to list: {} ({})", list.display_name, list.id);
+        }
+
+        let fetch_changed = |ids: &[String]| {
+            let mut tasks: Vec<TodoTask> = get_existing(client, ids, |id| format!("/me/todo/lists/{}/tasks/{}?{}", client::encode_segment(&list.id), client::encode_segment(id), client::TASK_EXPANSIONS))?;
+
+            if let Some(store) = attachments {
+                store.download(client, &list.id, &mut tasks)?;
+            }
+
+            Ok(tasks)
+        };
+
+        let delta_url = client.url(&format!("/me/todo/lists/{}/tasks/delta?$select=id", client::encode_segment(&list.id)));
+        let synced = delta::sync(client, snapshot, &delta_url, |task| &task.id, fetch_all, fetch_changed)?;
+        report_update(&list.display_name, &synced.update);
+
+        (synced.items, synced.delta_link)
+    } else {
+        (fetch_all()?, None)
+    };
+
+    Ok(ListBackup { list: list.clone(), tasks, delta_link })
+}
+
+/// Fetch the resource with each of the `ids`, at the path given by `path`, in batches. 
+/// Any which no longer exist are left out; the rest are returned in the order of their IDs. 
+fn get_existing<T, P>(client: &GraphClient, ids: &[String], path: P) -> Result<Vec<T>>
+    where T: serde::de::DeserializeOwned, P: Fn(&str) -> String
+{
+    let mut results = batch::get_each(client, ids.iter().map(|id| (id.clone(), path(id))).collect())?;
+
+    ids.iter()
+        .filter_map(|id| match results.remove(id)? {
+            Err(Error::GraphError(e)) if e.status == StatusCode::NOT_FOUND => None,
+            result => Some(result),
+        })
+        .collect()
+}
+
+/// Pick the lists to export, either from the arguments, or by asking the user. 
+fn select_lists(lists: Vec<TodoTaskList>, args: &ExportArgs) -> Result<Vec<TodoTaskList>> {
+    if args.all {
+        return Ok(lists);
+    }
+
+    if !args.lists.is_empty() {
+        return args.lists.iter()
+            .map(|selector| find_list(&lists, selector).cloned())
+            .collect();
+    }
+
+    if !super::is_interactive() {
+        return Err(Error::UsageError("No list was selected; use `--list` or `--all`".to_string()));
+    }
+
+    Ok(vec![prompt_for_list(&lists)?.clone()])
+}
+
+/// Find the list with the given ID or name. 
+/// The ID is checked first, then the exact name, then the name regardless of case. 
+fn find_list<'a>(lists: &'a [TodoTaskList], selector: &str) -> Result<&'a TodoTaskList> {
+    if let Some(list) = lists.iter().find(|list| list.id == selector) {
+        return Ok(list);
+    }
+
+    if let Some(list) = lists.iter().find(|list| list.display_name == selector) {
+        return Ok(list);
+    }
+
+    let matches: Vec<&TodoTaskList> = lists.iter()
+        .filter(|list| list.display_name.to_lowercase() == selector.to_lowercase())
+        .collect();
+
+    match matches.as_slice() {
+        [list] => Ok(list),
+        [] => Err(Error::UsageError(format!("No list named \"{}\" was found", selector))),
+        _ => Err(Error::UsageError(format!("More than one list is named \"{}\"; use its ID instead", selector))),
+    }
+}
+
+/// Show a menu of the `lists`, and ask the user to pick one, until they enter the number of one. 
+fn prompt_for_list(lists: &[TodoTaskList]) -> Result<&TodoTaskList> {
+    if lists.is_empty() {
+        return Err(Error::UsageError("There are no lists to choose from".to_string()));
+    }
+
+    eprintln!();
+    eprintln!("Todo Lists:");
+
+    for (i, list) in lists.iter().enumerate() {
+        eprintln!("{}. {}", i + 1, list.display_name);
+    }
+
+    eprintln!();
+    eprintln!("Enter number of list to fetch: ");
+
+    loop {
+        let mut index_str = String::new();
+        if io::stdin().read_line(&mut index_str)? == 0 {
+            return Err(Error::UsageError("No list was selected".to_string()));
+        }
+
+        let selected_list = index_str.trim().parse::<usize>().ok()
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| lists.get(index));
+
+        match selected_list {
+            Some(list) => break Ok(list),
+            None => eprintln!("Enter a number between 1 and {}", lists.len()),
+        }
+    }
+}
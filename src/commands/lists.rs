@@ -0,0 +1,11 @@
+use microsoft_todo_export::client::GraphClient;
+use microsoft_todo_export::error::Result;
+
+/// Print the ID and name of each of the user's task lists, separated by a tab. 
+pub fn lists(client: &GraphClient) -> Result<()> {
+    for list in client.lists().collect::<Result<Vec<_>>>()? {
+        println!("{}\t{}", list.id, list.display_name);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use microsoft_todo_export::error::Result;
+
+mod export;
+mod lists;
+mod restore;
+mod whoami;
+
+pub use export::export;
+pub use lists::lists;
+pub use restore::restore;
+pub use whoami::whoami;
+
+/// Can the user be prompted for input? 
+/// Prompts are only a fallback for when the required arguments haven't been given. 
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Open the file at `path` for writing, or standard output if no path is given. 
+/// Nothing replaces the file until the output is `finish`ed. 
+fn open_output(path: Option<&Path>) -> Result<Output> {
+    Ok(match path {
+        Some(path) => {
+            let mut temp_path = path.as_os_str().to_owned();
+            temp_path.push(".tmp");
+            let temp_path = PathBuf::from(temp_path);
+
+            let file = File::create(&temp_path)?;
+            Output::File { writer: io::BufWriter::new(file), temp_path, path: path.to_path_buf() }
+        },
+        None => Output::Stdout(io::BufWriter::new(io::stdout())),
+    })
+}
+
+/// Where the output of a command is written. 
+/// 
+/// A file is written to a temporary file alongside it first, so a failed export doesn't lose the existing file, 
+/// which matters most when the file is the backup an incremental export is updating. 
+enum Output {
+    File { writer: io::BufWriter<File>, temp_path: PathBuf, path: PathBuf },
+    Stdout(io::BufWriter<io::Stdout>),
+}
+
+impl Output {
+    /// Flush the output, and replace the file with it. 
+    fn finish(self) -> Result<()> {
+        match self {
+            Output::File { writer, temp_path, path } => {
+                let file = writer.into_inner().map_err(io::IntoInnerError::into_error)?;
+                file.sync_all()?;
+
+                fs::rename(&temp_path, &path)?;
+            },
+            Output::Stdout(mut writer) => writer.flush()?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::File { writer, .. } => writer.write(buf),
+            Output::Stdout(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::File { writer, .. } => writer.flush(),
+            Output::Stdout(writer) => writer.flush(),
+        }
+    }
+}
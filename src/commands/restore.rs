@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+
+use microsoft_todo_export::backup::Backup;
+use microsoft_todo_export::client::GraphClient;
+use microsoft_todo_export::error::Result;
+use microsoft_todo_export::export;
+use microsoft_todo_export::restore::Restorer;
+
+use crate::cli::{InputFormat, RestoreArgs};
+
+/// Recreate the lists and tasks of a backup in the signed in user's account. 
+pub fn restore(client: &GraphClient, args: &RestoreArgs) -> Result<()> {
+    let input = BufReader::new(File::open(&args.input)?);
+
+    let backup: Backup = match args.format {
+        InputFormat::Json => export::json::read(input)?,
+        InputFormat::TodoTxt => export::todotxt::read(input)?,
+    };
+
+    let mut restorer = Restorer::new(client, client.lists().collect::<Result<_>>()?)
+        .with_progress(|list, existing| match existing {
+            Some(existing) => eprintln!("Restoring {} tasks into the existing list: {}", list.tasks.len(), existing.display_name),
+            None => eprintln!("Restoring {} tasks into a new list: {}", list.tasks.len(), list.list.display_name),
+        });
+    let result = restorer.restore(&backup);
+
+    // Write the IDs even if the restore failed, so it's known what was created before the failure. 
+    if let Some(path) = &args.id_map {
+        let mut output = super::open_output(Some(path))?;
+        serde_json::to_writer_pretty(&mut output, restorer.ids()).map_err(io::Error::from)?;
+        output.finish()?;
+    }
+
+    result?;
+
+    eprintln!("Restored {} tasks into {} lists", restorer.ids().tasks.len(), restorer.ids().lists.len());
+
+    Ok(())
+}
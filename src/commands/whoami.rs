@@ -0,0 +1,16 @@
+use microsoft_todo_export::client::GraphClient;
+use microsoft_todo_export::error::Result;
+
+/// Print the signed in user. 
+pub fn whoami(client: &GraphClient) -> Result<()> {
+    let me = client.me()?;
+
+    println!("{} / {}", me.display_name, me.user_principal_name);
+    println!("ID: {}", me.id);
+
+    if let Some(mail) = me.mail {
+        println!("Mail: {}", mail);
+    }
+
+    Ok(())
+}
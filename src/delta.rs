@@ -0,0 +1,186 @@
+//! Delta queries; fetching only the items of a collection which have changed since it was last fetched.
+//!
+//! The first round of a delta query returns every item in the collection, and its last page gives a delta link.
+//! Following that link later returns only the items which have since been added, changed, or removed,
+//! and a new delta link, from which to continue next time.
+//!
+//! Delta queries of To Do resources don't support `$expand`, so only the IDs of changed items are requested,
+//! and the changed items are then fetched in full.
+//!
+//! See: <https://docs.microsoft.com/en-us/graph/delta-query-overview>
+
+use std::collections::{HashMap, HashSet};
+
+use reqwest::StatusCode;
+
+use crate::api::DeltaItem;
+use crate::client::GraphClient;
+use crate::error::{Error, Result};
+use crate::reader::CollectionReader;
+
+/// The items of a collection which have changed since a delta link was given.
+#[derive(Debug, Clone)]
+pub struct Changes {
+    /// The IDs of the items which were added or changed, in the order they were returned.
+    pub changed: Vec<String>,
+
+    /// The IDs of the items which were removed.
+    pub removed: Vec<String>,
+
+    /// The delta link from which to fetch the changes made after these.
+    pub delta_link: Option<String>,
+}
+
+/// Follow the delta query at `url` through every page, collecting the IDs of the items which changed.
+///
+/// An item may be returned more than once, if it changed again while the query was being read;
+/// only its last state is kept.
+pub fn fetch_changes(client: &GraphClient, url: &str) -> Result<Changes> {
+    let mut reader = CollectionReader::<DeltaItem>::new(client, url);
+
+    let mut order = Vec::new();
+    let mut is_removed = HashMap::new();
+
+    for item in reader.by_ref() {
+        let item = item?;
+
+        if is_removed.insert(item.id.clone(), item.removed.is_some()).is_none() {
+            order.push(item.id);
+        }
+    }
+
+    let (removed, changed) = order.into_iter().partition(|id| is_removed[id]);
+
+    Ok(Changes { changed, removed, delta_link: reader.delta_link().map(str::to_string) })
+}
+
+/// Apply `changes` to the items of an earlier `snapshot`.
+///
+/// Removed items are dropped, and changed items are replaced by their `fetched` versions, keeping their places.
+/// New items are added to the end, in the order they were fetched.
+/// Changed items which couldn't be fetched, because they were deleted in the meantime, are dropped too.
+pub fn apply<T>(snapshot: Vec<T>, changes: &Changes, fetched: Vec<T>, id: fn(&T) -> &str) -> Vec<T> {
+    let stale: HashSet<&str> = changes.removed.iter()
+        .chain(&changes.changed)
+        .map(String::as_str)
+        .collect();
+
+    let mut fetched: Vec<Option<T>> = fetched.into_iter().map(Some).collect();
+    let positions: HashMap<String, usize> = fetched.iter().enumerate()
+        .filter_map(|(i, item)| item.as_ref().map(|item| (id(item).to_string(), i)))
+        .collect();
+
+    let mut items: Vec<T> = snapshot.into_iter()
+        .filter_map(|item| match positions.get(id(&item)) {
+            Some(&i) => fetched[i].take(),
+            None if stale.contains(id(&item)) => None,
+            None => Some(item),
+        })
+        .collect();
+
+    items.extend(fetched.into_iter().flatten());
+    items
+}
+
+/// The current items of a collection, and the delta link from which to fetch later changes to it.
+pub struct Synced<T> {
+    pub items: Vec<T>,
+    pub delta_link: Option<String>,
+
+    /// How the items were brought up to date.
+    pub update: Update,
+}
+
+/// How a collection was brought up to date by `sync`.
+#[derive(Debug, Clone)]
+pub enum Update {
+    /// Only the items which had changed since the previous snapshot were fetched.
+    Changes(Changes),
+
+    /// Every item was fetched, as there was no previous snapshot to update.
+    Everything,
+
+    /// Every item was fetched, as the previous snapshot's delta link had expired.
+    Expired,
+}
+
+/// Bring the items of a collection up to date.
+///
+/// If there's a `previous` snapshot, and its delta link, only the items changed since it are fetched, with `fetch_changed`,
+/// which leaves out any which no longer exist.
+/// Otherwise, or if the delta link has expired, every item is fetched with `fetch_all`, and a new delta query is
+/// started from `delta_url`. The delta query is started first, so nothing which changes in between is missed.
+pub fn sync<T, A, C>(client: &GraphClient, previous: Option<(Vec<T>, &str)>, delta_url: &str, id: fn(&T) -> &str, fetch_all: A, fetch_changed: C) -> Result<Synced<T>>
+where
+    A: FnOnce() -> Result<Vec<T>>,
+    C: FnOnce(&[String]) -> Result<Vec<T>>,
+{
+    let mut update = Update::Everything;
+
+    if let Some((snapshot, delta_link)) = previous {
+        match fetch_changes(client, delta_link) {
+            Ok(changes) => {
+                let fetched = fetch_changed(&changes.changed)?;
+
+                return Ok(Synced {
+                    items: apply(snapshot, &changes, fetched, id),
+                    delta_link: changes.delta_link.clone(),
+                    update: Update::Changes(changes),
+                });
+            },
+
+            // Graph only keeps the state of a delta query for so long, after which it has to be started again.
+            Err(Error::GraphError(e)) if e.status == StatusCode::GONE => update = Update::Expired,
+
+            Err(e) => return Err(e),
+        }
+    }
+
+    let changes = fetch_changes(client, delta_url)?;
+
+    Ok(Synced { items: fetch_all()?, delta_link: changes.delta_link, update })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(changed: &[&str], removed: &[&str]) -> Changes {
+        Changes {
+            changed: changed.iter().map(|id| id.to_string()).collect(),
+            removed: removed.iter().map(|id| id.to_string()).collect(),
+            delta_link: None,
+        }
+    }
+
+    /// Items are IDs, with a version, eg: ("a", 1).
+    fn id(item: &(&'static str, u32)) -> &'static str {
+        item.0
+    }
+
+    #[test]
+    fn changes_are_applied_in_place() {
+        let snapshot = vec![("a", 1), ("b", 1), ("c", 1), ("d", 1)];
+
+        // "b" changed, "c" was removed, "e" was added, and "d" changed, but was deleted before it could be fetched.
+        let changes = changes(&["e", "b", "d"], &["c"]);
+        let fetched = vec![("e", 1), ("b", 2)];
+
+        assert_eq!(apply(snapshot, &changes, fetched, id), vec![("a", 1), ("b", 2), ("e", 1)]);
+    }
+
+    #[test]
+    fn no_changes_keep_the_snapshot() {
+        let snapshot = vec![("a", 1), ("b", 1)];
+
+        assert_eq!(apply(snapshot.clone(), &changes(&[], &[]), Vec::new(), id), snapshot);
+    }
+
+    #[test]
+    fn new_items_are_appended_in_the_order_they_were_fetched() {
+        let snapshot = vec![("a", 1)];
+        let fetched = vec![("c", 1), ("b", 1), ("a", 2)];
+
+        assert_eq!(apply(snapshot, &changes(&["c", "b", "a"], &[]), fetched, id), vec![("a", 2), ("c", 1), ("b", 1)]);
+    }
+}
@@ -1,14 +1,30 @@
+//! The errors which may occur when talking to Graph, or reading and writing backups.
+
 use std::convert::From;
+use std::fmt;
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
 #[derive(Debug, Display)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     IOError(::std::io::Error),
 
     ReqwestError(::reqwest::Error),
 
     ParseIntError(::std::num::ParseIntError),
+
+    /// The authorization server rejected a sign-in or token request.
+    AuthError(crate::auth::TokenError),
+
+    /// Graph responded to a request with an error.
+    GraphError(Box<GraphError>),
+
+    /// A request was throttled, or failed with a transient error, more times than the `RetryPolicy` allows.
+    RetriesExhausted(crate::retry::RetriesExhausted),
+
+    /// The program was used incorrectly, for example a list which doesn't exist was requested.
+    UsageError(String),
 }
 
 impl ::std::error::Error for Error {
@@ -17,7 +33,85 @@ impl ::std::error::Error for Error {
             Self::IOError(e) => Some(e),
             Self::ReqwestError(e) => Some(e),
             Self::ParseIntError(e) => Some(e),
+            Self::AuthError(_) => None,
+            Self::GraphError(_) => None,
+            Self::RetriesExhausted(e) => e.last_error.as_ref().map(|e| e as _),
+            Self::UsageError(_) => None,
+        }
+    }
+}
+
+/// An error response from the Graph API. 
+/// 
+/// The request IDs should be included when reporting a problem to Microsoft support. 
+/// 
+/// See: <https://docs.microsoft.com/en-us/graph/errors>
+#[derive(Debug, Clone)]
+pub struct GraphError {
+    /// The HTTP status of the response.
+    pub status: ::reqwest::StatusCode,
+
+    /// The Graph error code, eg: `itemNotFound`.
+    pub code: String,
+
+    /// A description of the error.
+    pub message: String,
+
+    /// The ID Graph assigned to the request.
+    pub request_id: Option<String>,
+
+    /// The ID the client assigned to the request, if any.
+    pub client_request_id: Option<String>,
+
+    /// When the error occurred.
+    pub date: Option<String>,
+}
+
+impl GraphError {
+    /// Build an error from the `status`, request ID headers, and `body` of an unsuccessful response. 
+    /// The request IDs in the headers are preferred to those in the body, which Graph often omits. 
+    pub fn from_response_parts(status: ::reqwest::StatusCode, request_id: Option<String>, client_request_id: Option<String>, body: &[u8]) -> Self {
+        match ::serde_json::from_slice::<crate::api::ErrorResponse>(body) {
+            Ok(response) => {
+                let inner = response.error.inner_error;
+
+                Self {
+                    status,
+                    code: response.error.code,
+                    message: response.error.message,
+                    request_id: request_id.or_else(|| inner.as_ref().and_then(|i| i.request_id.clone())),
+                    client_request_id: client_request_id.or_else(|| inner.as_ref().and_then(|i| i.client_request_id.clone())),
+                    date: inner.and_then(|i| i.date),
+                }
+            },
+
+            // Not every error comes from Graph itself, eg: errors from a proxy or load balancer. 
+            Err(_) => Self {
+                status,
+                code: status.canonical_reason().unwrap_or("unknown").to_string(),
+                message: String::from_utf8_lossy(body).trim().to_string(),
+                request_id,
+                client_request_id,
+                date: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Graph responded with {}: {}: {}", self.status, self.code, self.message)?;
+
+        let details: Vec<String> = [("request-id", &self.request_id), ("client-request-id", &self.client_request_id), ("date", &self.date)]
+            .iter()
+            .filter_map(|(name, value)| value.as_ref().map(|value| format!("{}: {}", name, value)))
+            .collect();
+
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
         }
+
+        Ok(())
     }
 }
 
@@ -40,3 +134,76 @@ impl From<::std::num::ParseIntError> for Error {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    const NOT_FOUND: &str = r#"{
+        "error": {
+            "code": "ErrorItemNotFound",
+            "message": "The specified object was not found in the store.",
+            "innerError": {
+                "date": "2021-05-03T09:00:00",
+                "request-id": "body-request",
+                "client-request-id": "body-client-request"
+            }
+        }
+    }"#;
+
+    #[test]
+    fn errors_with_an_inner_error() {
+        let error = GraphError::from_response_parts(StatusCode::NOT_FOUND, None, None, NOT_FOUND.as_bytes());
+
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert_eq!(error.code, "ErrorItemNotFound");
+        assert_eq!(error.message, "The specified object was not found in the store.");
+        assert_eq!(error.request_id.as_deref(), Some("body-request"));
+        assert_eq!(error.client_request_id.as_deref(), Some("body-client-request"));
+        assert_eq!(error.date.as_deref(), Some("2021-05-03T09:00:00"));
+        assert_eq!(
+            error.to_string(),
+            "Graph responded with 404 Not Found: ErrorItemNotFound: The specified object was not found in the store. \
+             (request-id: body-request, client-request-id: body-client-request, date: 2021-05-03T09:00:00)"
+        );
+    }
+
+    #[test]
+    fn errors_without_an_inner_error() {
+        let body = r#"{"error":{"code":"invalidRequest","message":"Invalid request"}}"#;
+        let error = GraphError::from_response_parts(StatusCode::BAD_REQUEST, None, None, body.as_bytes());
+
+        assert_eq!(error.code, "invalidRequest");
+        assert_eq!(error.message, "Invalid request");
+        assert_eq!(error.request_id, None);
+        assert_eq!(error.date, None);
+        assert_eq!(error.to_string(), "Graph responded with 400 Bad Request: invalidRequest: Invalid request");
+    }
+
+    #[test]
+    fn header_request_ids_are_preferred() {
+        let error = GraphError::from_response_parts(
+            StatusCode::NOT_FOUND,
+            Some("header-request".to_string()),
+            Some("header-client-request".to_string()),
+            NOT_FOUND.as_bytes(),
+        );
+
+        assert_eq!(error.request_id.as_deref(), Some("header-request"));
+        assert_eq!(error.client_request_id.as_deref(), Some("header-client-request"));
+        assert_eq!(error.date.as_deref(), Some("2021-05-03T09:00:00"));
+    }
+
+    #[test]
+    fn errors_from_proxies() {
+        let body = "<html><body><h1>502 Bad Gateway</h1></body></html>\n";
+        let error = GraphError::from_response_parts(StatusCode::BAD_GATEWAY, Some("header-request".to_string()), None, body.as_bytes());
+
+        assert_eq!(error.code, "Bad Gateway");
+        assert_eq!(error.message, "<html><body><h1>502 Bad Gateway</h1></body></html>");
+        assert_eq!(error.request_id.as_deref(), Some("header-request"));
+        assert_eq!(error.client_request_id, None);
+    }
+}
@@ -0,0 +1,246 @@
+//! A CSV file, with a row per task, and a column for each of the properties chosen.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::api::tasks::{TodoTask, TodoTaskList};
+use crate::backup::Backup;
+use crate::error::Result;
+
+/// A column of a CSV export. 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// The name of the list the task is in.
+    List,
+    Id,
+    Title,
+    Status,
+    Importance,
+    Created,
+    Modified,
+    Completed,
+    Due,
+    Reminder,
+    Start,
+    /// The categories of the task, separated by commas.
+    Categories,
+    /// The body of the task, as plain text.
+    Body,
+    /// A human readable summary of how the task recurs.
+    Recurrence,
+
+    /// The steps of the task, one per line, each preceded by whether it's been done, eg: `[x] Buy eggs`.
+    Checklist,
+}
+
+impl Column {
+    /// Every column, in the order they're written by default. 
+    pub const ALL: &'static [Column] = &[
+        Self::List,
+        Self::Id,
+        Self::Title,
+        Self::Status,
+        Self::Importance,
+        Self::Created,
+        Self::Modified,
+        Self::Completed,
+        Self::Due,
+        Self::Reminder,
+        Self::Start,
+        Self::Categories,
+        Self::Body,
+        Self::Recurrence,
+        Self::Checklist,
+    ];
+
+    /// The columns written when none are chosen; every column except the ID. 
+    pub const DEFAULT: &'static [Column] = &[
+        Self::List,
+        Self::Title,
+        Self::Status,
+        Self::Importance,
+        Self::Created,
+        Self::Modified,
+        Self::Completed,
+        Self::Due,
+        Self::Reminder,
+        Self::Start,
+        Self::Categories,
+        Self::Body,
+        Self::Recurrence,
+        Self::Checklist,
+    ];
+
+    /// The name of the column, used both in its header, and to choose it. 
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Id => "id",
+            Self::Title => "title",
+            Self::Status => "status",
+            Self::Importance => "importance",
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Completed => "completed",
+            Self::Due => "due",
+            Self::Reminder => "reminder",
+            Self::Start => "start",
+            Self::Categories => "categories",
+            Self::Body => "body",
+            Self::Recurrence => "recurrence",
+            Self::Checklist => "checklist",
+        }
+    }
+
+    /// The value of this column for the given `task`, within the `list`. 
+    fn value(self, list: &TodoTaskList, task: &TodoTask) -> String {
+        fn optional<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
+        }
+
+        match self {
+            Self::List => list.display_name.clone(),
+            Self::Id => task.id.clone(),
+            Self::Title => task.title.clone(),
+            Self::Status => task.status.to_string(),
+            Self::Importance => task.importance.to_string(),
+            Self::Created => task.created_date_time.to_string(),
+            Self::Modified => task.last_modified_date_time.to_string(),
+            Self::Completed => optional(&task.completed_date_time),
+            Self::Due => optional(&task.due_date_time),
+            Self::Reminder => optional(&task.reminder_date_time),
+            Self::Start => optional(&task.start_date_time),
+            Self::Categories => task.categories.join(", "),
+            Self::Body => super::body_text(&task.body),
+            Self::Recurrence => optional(&task.recurrence),
+            Self::Checklist => task.checklist_items.iter()
+                .map(|item| format!("[{}] {}", if item.is_checked { "x" } else { " " }, item.display_name))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        Self::ALL.iter()
+            .find(|column| column.name().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|column| column.name()).collect();
+                format!("unknown column \"{}\"; expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Write one row per task, with the given `columns`, preceded by a header row. 
+pub fn write<W: Write>(output: &mut W, backup: &Backup, columns: &[Column]) -> Result<()> {
+    let mut writer = ::csv::Writer::from_writer(output);
+
+    writer.write_record(columns.iter().map(|column| column.name())).map_err(io::Error::from)?;
+
+    for list in &backup.lists {
+        for task in &list.tasks {
+            writer.write_record(columns.iter().map(|column| column.value(&list.list, task))).map_err(io::Error::from)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::tasks::fixtures::{list, task};
+    use crate::backup::ListBackup;
+
+    fn written(tasks: Vec<TodoTask>, columns: &[Column]) -> String {
+        let mut backup = Backup::new(None);
+        backup.lists.push(ListBackup { list: list("Home, sweet home"), tasks, delta_link: None });
+
+        let mut output = Vec::new();
+        write(&mut output, &backup, columns).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    /// The rows of a CSV file, including its header.
+    fn rows(csv: &str) -> Vec<Vec<String>> {
+        ::csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(csv.as_bytes())
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect()
+    }
+
+    #[test]
+    fn columns_are_written_in_the_order_chosen() {
+        let tasks = vec![
+            task(json!({ "id": "1", "title": "Buy milk", "importance": "high" })),
+            task(json!({
+                "id": "2",
+                "title": "Fix the boiler",
+                "status": "inProgress",
+                "dueDateTime": { "dateTime": "2021-05-01T09:00:00.0000000", "timeZone": "UTC" },
+            })),
+        ];
+
+        let columns: Vec<Column> = "due,TITLE, id ,list".split(',').map(|name| name.parse().unwrap()).collect();
+        assert_eq!(columns, vec![Column::Due, Column::Title, Column::Id, Column::List]);
+
+        assert_eq!(rows(&written(tasks, &columns)), vec![
+            vec!["due", "title", "id", "list"],
+            vec!["", "Buy milk", "1", "Home, sweet home"],
+            vec!["2021-05-01T09:00:00 (UTC)", "Fix the boiler", "2", "Home, sweet home"],
+        ]);
+    }
+
+    #[test]
+    fn every_column_by_default_except_the_id() {
+        let csv = written(vec![task(json!({ "categories": ["Red", "Blue"] }))], Column::DEFAULT);
+        let rows = rows(&csv);
+
+        let names: Vec<&str> = Column::ALL.iter().filter(|column| **column != Column::Id).map(|column| column.name()).collect();
+        assert_eq!(rows[0], names);
+        assert_eq!(rows[1][..6], ["Home, sweet home", "Buy milk", "Not started", "Normal", "2021-03-01T10:00:00Z", "2021-03-01T10:00:00Z"]);
+        assert_eq!(rows[1][10], "Red, Blue");
+    }
+
+    #[test]
+    fn unknown_columns_are_rejected() {
+        let error = "title,colour".split(',').map(str::parse::<Column>).collect::<std::result::Result<Vec<_>, _>>().unwrap_err();
+
+        assert!(error.starts_with("unknown column \"colour\"; expected one of: list, id, title,"), "{}", error);
+    }
+
+    #[test]
+    fn values_with_commas_and_newlines_are_quoted() {
+        let task = task(json!({
+            "title": "Pack, then \"leave\"",
+            "body": { "content": "<p>Passport, tickets</p><p>Keys</p>", "contentType": "html" },
+            "checklistItems": [
+                { "id": "1", "displayName": "Socks, shirts", "isChecked": true, "createdDateTime": "2021-03-01T10:00:00Z" },
+                { "id": "2", "displayName": "Charger", "isChecked": false, "createdDateTime": "2021-03-01T10:00:00Z" },
+            ],
+        }));
+
+        let csv = written(vec![task], &[Column::Title, Column::Body, Column::Checklist]);
+
+        assert_eq!(csv, "title,body,checklist\n\"Pack, then \"\"leave\"\"\",\"Passport, tickets\nKeys\",\"[x] Socks, shirts\n[ ] Charger\"\n");
+        assert_eq!(rows(&csv)[1], ["Pack, then \"leave\"", "Passport, tickets\nKeys", "[x] Socks, shirts\n[ ] Charger"]);
+    }
+}
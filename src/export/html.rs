@@ -0,0 +1,485 @@
+//! A deliberately small HTML converter for task bodies.
+//!
+//! Task bodies are simple; paragraphs, line breaks, a little formatting and perhaps a list or a link,
+//! so this doesn't attempt to handle arbitrary HTML.
+
+/// Convert the `html` body of a task to plain text.
+pub fn to_text(html: &str) -> String {
+    let mut text = String::new();
+
+    // The number of lists the text is within; nested lists are indented.
+    let mut lists: usize = 0;
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(t) => text.push_str(&collapse_whitespace(&decode_entities(t), text.ends_with(|c: char| c.is_whitespace()) || text.is_empty())),
+            Token::Tag(tag) => match tag.name.as_str() {
+                "br" => text.push('\n'),
+                "ul" | "ol" if !tag.closing => {
+                    ensure_line_start(&mut text);
+                    lists += 1;
+                },
+                "ul" | "ol" => {
+                    lists = lists.saturating_sub(1);
+
+                    if lists == 0 {
+                        text.push('\n');
+                    }
+                },
+                "p" | "div" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table" if tag.closing => text.push('\n'),
+                "li" if !tag.closing => {
+                    ensure_line_start(&mut text);
+                    text.push_str(&"  ".repeat(lists.saturating_sub(1)));
+                    text.push_str("- ");
+                },
+                "li" if tag.closing => ensure_line_start(&mut text),
+                "td" | "th" if tag.closing => text.push('\t'),
+                _ => {},
+            },
+        }
+    }
+
+    tidy_lines(&text)
+}
+
+/// Convert the `html` body of a task to Markdown, keeping its paragraphs, lists, links and emphasis.
+pub fn to_markdown(html: &str) -> String {
+    let mut markdown = String::new();
+
+    // Whether each open list is ordered, and the number of its next item.
+    let mut lists: Vec<Option<usize>> = Vec::new();
+
+    // The destination of each open link, if it has one.
+    let mut links: Vec<Option<String>> = Vec::new();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(t) => {
+                let at_whitespace = markdown.ends_with(|c: char| c.is_whitespace()) || markdown.is_empty();
+                markdown.push_str(&super::markdown::escape(&collapse_whitespace(&decode_entities(t), at_whitespace)));
+            },
+            Token::Tag(tag) => match (tag.name.as_str(), tag.closing) {
+                // A hard line break, unless there's nothing on the line to break from.
+                ("br", _) if markdown.is_empty() || markdown.ends_with('\n') => markdown.push('\n'),
+                ("br", _) => markdown.push_str("\\\n"),
+                ("p" | "div" | "table" | "tr", true) => {
+                    // A break at the end of a block would be read as a literal backslash.
+                    if markdown.ends_with("\\\n") {
+                        markdown.truncate(markdown.len() - 2);
+                    }
+
+                    markdown.push_str("\n\n");
+                },
+                ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                    ensure_line_start(&mut markdown);
+                    markdown.push('\n');
+
+                    let level = tag.name[1..].parse().unwrap_or(1);
+                    markdown.push_str(&"#".repeat(level));
+                    markdown.push(' ');
+                },
+                ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => markdown.push_str("\n\n"),
+                ("b" | "strong", _) => markdown.push_str("**"),
+                ("i" | "em", _) => markdown.push('*'),
+                ("s" | "strike" | "del", _) => markdown.push_str("~~"),
+                ("code", _) => markdown.push('`'),
+                ("td" | "th", true) => markdown.push(' '),
+                ("a", false) => {
+                    let href = tag.attribute("href").filter(|href| !href.is_empty()).map(str::to_string);
+
+                    if href.is_some() {
+                        markdown.push('[');
+                    }
+
+                    links.push(href);
+                },
+                ("a", true) => {
+                    if let Some(Some(href)) = links.pop() {
+                        markdown.push_str(&format!("]({})", href.replace(' ', "%20").replace(')', "%29")));
+                    }
+                },
+                ("ul" | "ol", false) => {
+                    ensure_line_start(&mut markdown);
+                    lists.push(if tag.name == "ol" { Some(1) } else { None });
+                },
+                ("ul" | "ol", true) => {
+                    lists.pop();
+
+                    if lists.is_empty() {
+                        markdown.push('\n');
+                    }
+                },
+                ("li", false) => {
+                    ensure_line_start(&mut markdown);
+                    markdown.push_str(&"   ".repeat(lists.len().saturating_sub(1)));
+
+                    match lists.last_mut() {
+                        Some(Some(number)) => {
+                            markdown.push_str(&format!("{}. ", number));
+                            *number += 1;
+                        },
+                        _ => markdown.push_str("- "),
+                    }
+                },
+                ("li", true) => ensure_line_start(&mut markdown),
+                _ => {},
+            },
+        }
+    }
+
+    tidy_lines(&markdown)
+}
+
+/// A piece of an HTML document.
+#[derive(Debug)]
+pub(crate) enum Token<'a> {
+    /// Text between tags, with its entities still encoded.
+    Text(&'a str),
+
+    Tag(Tag),
+}
+
+/// An opening or closing tag.
+#[derive(Debug)]
+pub(crate) struct Tag {
+    /// The tag name, in lower case.
+    pub name: String,
+
+    /// Is this a closing tag, eg: `</p>`?
+    pub closing: bool,
+
+    /// The attributes of an opening tag, with their names in lower case, and their values decoded.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    /// The value of the attribute with the given (lower case) `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Split the `html` into text and tags, skipping comments and the contents of `head`, `style` and `script` elements.
+pub(crate) fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    let mut skip_until: Option<&str> = None;
+
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+
+        // A `<` which doesn't start a tag, eg: "a < b", is just text.
+        let starts_tag = rest.strip_prefix('<')
+            .and_then(|after| after.chars().next())
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '/');
+
+        if !starts_tag {
+            let skip = if rest.starts_with('<') { 1 } else { 0 };
+            let end = rest[skip..].find('<').map_or(rest.len(), |end| end + skip);
+
+            if skip_until.is_none() {
+                tokens.push(Token::Text(&rest[..end]));
+            }
+
+            rest = &rest[end..];
+            continue;
+        }
+
+        // An unterminated tag is just text.
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => {
+                if skip_until.is_none() {
+                    tokens.push(Token::Text(rest));
+                }
+
+                break;
+            }
+        };
+
+        let inner = rest[1..end].trim().trim_end_matches('/');
+        rest = &rest[end + 1..];
+
+        let closing = inner.starts_with('/');
+        let inner = inner.trim_start_matches('/');
+        let name_end = inner.find(|c: char| c.is_whitespace()).unwrap_or(inner.len());
+        let name = inner[..name_end].to_ascii_lowercase();
+        let attributes = if closing { Vec::new() } else { parse_attributes(&inner[name_end..]) };
+
+        if let Some(skipped) = skip_until {
+            if closing && name == skipped {
+                skip_until = None;
+            }
+
+            continue;
+        }
+
+        if !closing {
+            skip_until = match name.as_str() {
+                "head" => Some("head"),
+                "style" => Some("style"),
+                "script" => Some("script"),
+                _ => None,
+            };
+
+            if skip_until.is_some() {
+                continue;
+            }
+        }
+
+        tokens.push(Token::Tag(Tag { name, closing, attributes }));
+    }
+
+    tokens
+}
+
+/// Parse the attributes of a tag, eg: `href="https://example.com" target=_blank hidden`.
+fn parse_attributes(mut rest: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let name_end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+
+                let (value, remaining) = match after.chars().next() {
+                    Some(quote) if quote == '"' || quote == '\'' => {
+                        let end = after[1..].find(quote).map_or(after.len(), |end| end + 1);
+                        (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                    },
+                    _ => {
+                        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    },
+                };
+
+                rest = remaining;
+                decode_entities(value)
+            },
+            None => String::new(),
+        };
+
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+    }
+
+    attributes
+}
+
+/// Decode the character references in `text`, eg: `&amp;` and `&#39;`.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ if entity.starts_with("#x") || entity.starts_with("#X") => u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+
+        match character {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Collapse runs of whitespace, including line breaks in the source, into single spaces, as a browser would.
+/// Leading whitespace is removed if the output already ends in whitespace.
+pub(crate) fn collapse_whitespace(text: &str, at_whitespace: bool) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut previous_whitespace = at_whitespace;
+
+    for c in text.chars() {
+        // Non-breaking spaces are kept as regular spaces, but never collapsed.
+        if c == '\u{a0}' {
+            collapsed.push(' ');
+            previous_whitespace = false;
+        } else if c.is_whitespace() {
+            if !previous_whitespace {
+                collapsed.push(' ');
+            }
+
+            previous_whitespace = true;
+        } else {
+            collapsed.push(c);
+            previous_whitespace = false;
+        }
+    }
+
+    collapsed
+}
+
+/// Start a new line, unless the `text` is already at the start of one.
+pub(crate) fn ensure_line_start(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+/// Trim trailing whitespace from each line, collapse runs of blank lines, and trim the start and end of the text.
+pub(crate) fn tidy_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+
+        lines.push(if line.trim().is_empty() { "" } else { line });
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A body as Outlook and To Do write them, with a head, styles, and non-breaking spaces.
+    const OUTLOOK: &str = r#"<html><head>
+<meta http-equiv="Content-Type" content="text/html; charset=utf-8">
+<style type="text/css" style="display:none;"> P {margin-top:0;margin-bottom:0;} </style>
+</head>
+<body dir="ltr">
+<div style="font-family: Calibri, Arial, Helvetica, sans-serif; font-size: 12pt; color: rgb(0, 0, 0);">
+Call the&nbsp;&nbsp;plumber about the <b>boiler</b> &amp; the sink.</div>
+<div>
+<ul>
+<li>Ask about:
+<ol>
+<li>the <a href="https://example.com/quote?id=1&amp;v=2">quote</a></li>
+<li><i>a date</i></li>
+</ol>
+</li>
+<li>Pay the deposit</li>
+</ul>
+</div>
+<div><br>
+</div>
+<!-- <p>A comment</p> -->
+<div>Thanks,<br>Sam</div>
+</body>
+</html>"#;
+
+    #[test]
+    fn outlook_bodies_as_text() {
+        assert_eq!(to_text(OUTLOOK), [
+            "Call the  plumber about the boiler & the sink.",
+            "- Ask about:",
+            "  - the quote",
+            "  - a date",
+            "- Pay the deposit",
+            "",
+            "Thanks,",
+            "Sam",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn outlook_bodies_as_markdown() {
+        assert_eq!(to_markdown(OUTLOOK), [
+            "Call the  plumber about the **boiler** & the sink.",
+            "",
+            "- Ask about:",
+            "   1. the [quote](https://example.com/quote?id=1&v=2)",
+            "   2. *a date*",
+            "- Pay the deposit",
+            "",
+            "Thanks,\\",
+            "Sam",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn markdown_is_escaped() {
+        assert_eq!(
+            to_markdown("<p>Use *stars*, _lines_ and [brackets] for #1 &lt;tags&gt; in C:\\Temp</p>"),
+            r"Use \*stars\*, \_lines\_ and \[brackets\] for \#1 \<tags\> in C:\\Temp"
+        );
+
+        // Link destinations mustn't end the link early.
+        assert_eq!(to_markdown(r#"<a href="https://example.com/a file (1).txt">file</a>"#), "[file](https://example.com/a%20file%20(1%29.txt)");
+
+        // Links without a destination are just their text.
+        assert_eq!(to_markdown("<a name=top>Top</a>"), "Top");
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(decode_entities("&#39;&#x41;&quot;&apos;&lt;&gt;&amp;"), "'A\"'<>&");
+        assert_eq!(decode_entities("AT&T &unknown; & &#xZZ; &"), "AT&T &unknown; & &#xZZ; &");
+        assert_eq!(to_text("Tom&nbsp;&amp;&nbsp;Jerry"), "Tom & Jerry");
+    }
+
+    #[test]
+    fn tags_and_attributes() {
+        let tokens = tokenize(r#"<A HREF='x.html' target=_blank Hidden>a < b</a><script>alert("<b>")</script><br/>"#);
+
+        match &tokens[0] {
+            Token::Tag(tag) => {
+                assert_eq!(tag.name, "a");
+                assert!(!tag.closing);
+                assert_eq!(tag.attribute("href"), Some("x.html"));
+                assert_eq!(tag.attribute("target"), Some("_blank"));
+                assert_eq!(tag.attribute("hidden"), Some(""));
+            },
+            token => panic!("expected a tag, got: {:?}", token),
+        }
+
+        // A `<` which doesn't start a tag is text, and scripts are skipped entirely.
+        assert!(matches!(tokens[1], Token::Text("a ")));
+        assert!(matches!(tokens[2], Token::Text("< b")));
+        assert!(matches!(&tokens[3], Token::Tag(tag) if tag.name == "a" && tag.closing));
+        assert!(matches!(&tokens[4], Token::Tag(tag) if tag.name == "br" && !tag.closing));
+        assert_eq!(tokens.len(), 5);
+
+        // As is a tag which is never closed.
+        assert_eq!(to_text("1 <2 and 3 > 2"), "1 <2 and 3 > 2");
+        assert_eq!(to_text("Done <b"), "Done <b");
+        assert_eq!(to_text("é < ü"), "é < ü");
+    }
+}
@@ -0,0 +1,869 @@
+//! An iCalendar document containing a `VTODO` for each task, which may be imported into CalDAV servers,
+//! Thunderbird, and most other calendar applications.
+//!
+//! Dates in a known time zone are written in that zone, alongside a `VTIMEZONE` describing it.
+//! Zones are identified by their IANA names, which most clients will recognise, and use their own rules for.
+//! Dates in an unknown zone are written as "floating" local times, and, where iCalendar requires UTC,
+//! are assumed to already be in UTC.
+//!
+//! See: <https://tools.ietf.org/html/rfc5545>
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
+
+use crate::api::{Date, DateTimeOffset, DateTimeTimeZone};
+use crate::api::tasks::{ChecklistItem, DayOfWeek, Importance, PatternedRecurrence, RecurrencePattern, RecurrenceRange, TaskStatus, TodoTask, TodoTaskList, WeekIndex};
+use crate::backup::Backup;
+use crate::error::Result;
+use crate::timezone::{Transition, Zone};
+
+/// The longest a content line may be, in bytes, before it must be folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Write every task in the `backup` as a `VTODO` within a single calendar.
+pub fn write<W: Write>(output: &mut W, backup: &Backup) -> Result<()> {
+    let stamp = parse_offset(&backup.exported_at).unwrap_or_else(|| Utc::now().naive_utc());
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//microsoft-todo-export//{}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    // Each zone needs to be described once, however many tasks use it, over the years they use it in.
+    let mut zones: BTreeMap<&str, (Zone, i32, i32)> = BTreeMap::new();
+
+    let times = backup.lists.iter()
+        .flat_map(|list| &list.tasks)
+        .flat_map(|task| task.due_date_time.iter().chain(&task.start_date_time));
+
+    for time in times {
+        if let Some(IcalTime::Zoned(zone, local)) = IcalTime::parse(time) {
+            let (_, first, last) = zones.entry(zone.iana).or_insert((zone, local.year(), local.year()));
+            *first = (*first).min(local.year());
+            *last = (*last).max(local.year());
+        }
+    }
+
+    for (zone, first, last) in zones.values() {
+        vtimezone(&mut lines, zone, *first, *last);
+    }
+
+    for list in &backup.lists {
+        for task in &list.tasks {
+            vtodo(&mut lines, &list.list, task, stamp);
+
+            for item in &task.checklist_items {
+                checklist_vtodo(&mut lines, &list.list, task, item, stamp);
+            }
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in &lines {
+        write!(output, "{}\r\n", fold(line))?;
+    }
+
+    Ok(())
+}
+
+/// A date and time, as it will be written to the calendar.
+enum IcalTime {
+    Utc(NaiveDateTime),
+
+    /// A local time in a known zone.
+    Zoned(Zone, NaiveDateTime),
+
+    /// A local time in a zone we don't know the rules of.
+    Floating(NaiveDateTime),
+}
+
+impl IcalTime {
+    /// Parse a `DateTimeTimeZone`, eg: "2020-01-01T09:30:00.0000000" in "Pacific Standard Time".
+    fn parse(value: &DateTimeTimeZone) -> Option<Self> {
+        let local = value.local()?;
+
+        Some(match value.zone() {
+            Some(zone) if zone.name == "UTC" => Self::Utc(local),
+            Some(zone) => Self::Zoned(zone, local),
+            None => Self::Floating(local),
+        })
+    }
+
+    /// A property with this time as its value, in its own zone, eg: `DUE;TZID=America/Los_Angeles:20210501T090000`.
+    fn property(&self, name: &str) -> String {
+        match self {
+            Self::Utc(time) => format!("{}:{}Z", name, format_local(time)),
+            Self::Zoned(zone, time) => format!("{};TZID={}:{}", name, param_value(zone.iana), format_local(time)),
+            Self::Floating(time) => format!("{}:{}", name, format_local(time)),
+        }
+    }
+
+    /// The `UNTIL` of a recurrence starting at this time, which ends at the end of the `date`.
+    /// It must be in UTC, unless this is a floating time.
+    fn until(&self, date: NaiveDate) -> String {
+        let end = date.and_hms_opt(23, 59, 59).unwrap_or_default();
+
+        match self {
+            Self::Floating(_) => format_local(&end),
+            Self::Zoned(zone, _) => format_utc(&zone.local_to_utc(end)),
+            Self::Utc(_) => format_utc(&end),
+        }
+    }
+}
+
+/// Describe the offsets of the `zone`, and the changes between them, over the years from `first` to `last`.
+///
+/// Changes which follow a yearly rule, eg: "the last Sunday of March", until at least two years after the `last` year,
+/// are written as a single observance with an `RRULE`, from the first year they follow it. Any before then are written
+/// individually, as are all of the changes of a zone whose rules aren't regular, however far they extend.
+fn vtimezone(lines: &mut Vec<String>, zone: &Zone, first: i32, last: i32) {
+    let start = year_start(first);
+
+    let mut years: Vec<Vec<Transition>> = (first..=last + 2).map(|_| Vec::new()).collect();
+    for transition in zone.transitions(start - Duration::days(1), year_start(last + 4)) {
+        let year = transition.local().year();
+
+        if (first..=last + 2).contains(&year) {
+            years[(year - first) as usize].push(transition);
+        }
+    }
+
+    // The first year from which every change follows a yearly rule, and those rules.
+    let ruled = (0..=(last - first) as usize).find_map(|from| {
+        let rules = years[from].iter()
+            .enumerate()
+            .map(|(index, transition)| YearlyRule::matching(transition, index, &years[from + 1..]))
+            .collect::<Option<Vec<_>>>()?;
+
+        // Each following year must have the same changes, and no others.
+        years[from + 1..].iter().all(|year| year.len() == rules.len()).then_some((from, rules))
+    });
+
+    lines.push("BEGIN:VTIMEZONE".to_string());
+    lines.push(format!("TZID:{}", zone.iana));
+    lines.push(format!("X-LIC-LOCATION:{}", zone.iana));
+
+    // The offset in force from the start of the first year, until the first change.
+    let (offset, daylight) = zone.offset_at(zone.local_to_utc(start));
+    observance(lines, daylight, &start, offset, offset, None);
+
+    let individual = ruled.as_ref().map_or(years.len(), |(from, _)| *from);
+
+    for transition in years[..individual].iter().flatten() {
+        observance(lines, transition.daylight, &transition.local(), transition.from, transition.to, None);
+    }
+
+    if let Some((from, rules)) = ruled {
+        for (transition, rule) in years[from].iter().zip(rules) {
+            observance(lines, transition.daylight, &transition.local(), transition.from, transition.to, Some(&rule.to_string()));
+        }
+    }
+
+    lines.push("END:VTIMEZONE".to_string());
+}
+
+/// A `STANDARD` or `DAYLIGHT` observance, which starts at the local time `start`, and repeats according to the `rule`, if any.
+fn observance(lines: &mut Vec<String>, daylight: bool, start: &NaiveDateTime, from: i32, to: i32, rule: Option<&str>) {
+    let kind = if daylight { "DAYLIGHT" } else { "STANDARD" };
+
+    lines.push(format!("BEGIN:{}", kind));
+    lines.push(format!("DTSTART:{}", format_local(start)));
+
+    if let Some(rule) = rule {
+        lines.push(format!("RRULE:{}", rule));
+    }
+
+    lines.push(format!("TZOFFSETFROM:{}", format_offset(from)));
+    lines.push(format!("TZOFFSETTO:{}", format_offset(to)));
+    lines.push(format!("END:{}", kind));
+}
+
+/// A change which happens each year on the nth, or last, weekday of a month, at the same local time.
+struct YearlyRule {
+    month: u32,
+
+    /// The week of the month, from 1 to 5, or -1 for the last week.
+    week: i32,
+
+    weekday: Weekday,
+}
+
+impl YearlyRule {
+    /// The rule which the `transition`, the change at `index` within its year, and the change at the same `index`
+    /// in each of the following `years`, all follow.
+    fn matching(transition: &Transition, index: usize, years: &[Vec<Transition>]) -> Option<Self> {
+        let date = transition.local().date();
+
+        let candidates = vec![
+            Self { month: date.month(), week: ((date.day() - 1) / 7 + 1) as i32, weekday: date.weekday() },
+            Self { month: date.month(), week: -1, weekday: date.weekday() },
+        ];
+
+        candidates.into_iter()
+            .filter(|rule| rule.date(date.year()) == Some(date))
+            .find(|rule| years.iter().enumerate().all(|(i, year)| {
+                year.get(index).is_some_and(|next| {
+                    rule.date(date.year() + i as i32 + 1) == Some(next.local().date())
+                        && next.local().time() == transition.local().time()
+                        && (next.from, next.to, next.daylight) == (transition.from, transition.to, transition.daylight)
+                })
+            }))
+    }
+
+    /// The date of the change in the given `year`.
+    fn date(&self, year: i32) -> Option<NaiveDate> {
+        match self.week {
+            -1 => {
+                let (next_year, next_month) = if self.month == 12 { (year + 1, 1) } else { (year, self.month + 1) };
+                let last = NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()?;
+                let days_back = (7 + last.weekday().num_days_from_monday() - self.weekday.num_days_from_monday()) % 7;
+
+                Some(last - Duration::days(i64::from(days_back)))
+            },
+            week => NaiveDate::from_weekday_of_month_opt(year, self.month, self.weekday, week as u8),
+        }
+    }
+}
+
+/// Eg: `FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU`.
+impl fmt::Display for YearlyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ=YEARLY;BYMONTH={};BYDAY={}{}", self.month, self.week, weekday_code(self.weekday))
+    }
+}
+
+/// Midnight at the start of the `year`.
+fn year_start(year: i32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, 1, 1).and_then(|date| date.and_hms_opt(0, 0, 0)).unwrap_or_default()
+}
+
+/// A single task, within the `list`.
+fn vtodo(lines: &mut Vec<String>, list: &TodoTaskList, task: &TodoTask, stamp: NaiveDateTime) {
+    lines.push("BEGIN:VTODO".to_string());
+    lines.push(format!("UID:{}", escape_text(&task.id)));
+    lines.push(format!("DTSTAMP:{}", format_utc(&stamp)));
+
+    if let Some(created) = parse_offset(&task.created_date_time) {
+        lines.push(format!("CREATED:{}", format_utc(&created)));
+    }
+
+    if let Some(modified) = parse_offset(&task.last_modified_date_time) {
+        lines.push(format!("LAST-MODIFIED:{}", format_utc(&modified)));
+    }
+
+    lines.push(format!("SUMMARY:{}", escape_text(&task.title)));
+
+    let description = super::body_text(&task.body);
+    if !description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+    }
+
+    // The list name lets the tasks of each list be told apart after they're imported.
+    let categories: Vec<String> = std::iter::once(&list.display_name)
+        .chain(&task.categories)
+        .map(|category| escape_text(category))
+        .collect();
+    lines.push(format!("CATEGORIES:{}", categories.join(",")));
+
+    lines.push(format!("PRIORITY:{}", priority(&task.importance)));
+    lines.push(format!("STATUS:{}", status(&task.status)));
+
+    let due = task.due_date_time.as_ref().and_then(IcalTime::parse);
+
+    // A recurring task starts at its first occurrence, rather than its start date.
+    if let Some(recurrence) = &task.recurrence {
+        recurrence_properties(lines, recurrence, due.as_ref());
+    } else if let Some(start) = task.start_date_time.as_ref().and_then(IcalTime::parse) {
+        lines.push(start.property("DTSTART"));
+    }
+
+    if let Some(due) = &due {
+        lines.push(due.property("DUE"));
+    }
+
+    // COMPLETED must always be in UTC.
+    if let Some(completed) = task.completed_date_time.as_ref().and_then(utc) {
+        lines.push(format!("COMPLETED:{}", format_utc(&completed)));
+    }
+
+    if task.is_reminder_on {
+        if let Some(reminder) = task.reminder_date_time.as_ref().and_then(utc) {
+            lines.push("BEGIN:VALARM".to_string());
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", escape_text(&task.title)));
+            lines.push(format!("TRIGGER;VALUE=DATE-TIME:{}", format_utc(&reminder)));
+            lines.push("END:VALARM".to_string());
+        }
+    }
+
+    lines.push("END:VTODO".to_string());
+}
+
+/// A step of a task, as a `VTODO` related to the task's own.
+fn checklist_vtodo(lines: &mut Vec<String>, list: &TodoTaskList, task: &TodoTask, item: &ChecklistItem, stamp: NaiveDateTime) {
+    lines.push("BEGIN:VTODO".to_string());
+    lines.push(format!("UID:{}", escape_text(&item.id)));
+    lines.push(format!("DTSTAMP:{}", format_utc(&stamp)));
+
+    if let Some(created) = parse_offset(&item.created_date_time) {
+        lines.push(format!("CREATED:{}", format_utc(&created)));
+    }
+
+    lines.push(format!("SUMMARY:{}", escape_text(&item.display_name)));
+    lines.push(format!("CATEGORIES:{}", escape_text(&list.display_name)));
+    lines.push(format!("RELATED-TO;RELTYPE=PARENT:{}", escape_text(&task.id)));
+    lines.push(format!("STATUS:{}", if item.is_checked { "COMPLETED" } else { "NEEDS-ACTION" }));
+
+    if let Some(checked) = item.checked_date_time.as_ref().and_then(parse_offset) {
+        lines.push(format!("COMPLETED:{}", format_utc(&checked)));
+    }
+
+    lines.push("END:VTODO".to_string());
+}
+
+/// The `DTSTART` and `RRULE` of a recurring task.
+///
+/// A recurrence needs a start; the due date is used if there is one, as Graph requires it to be
+/// the first occurrence, otherwise the start of the range is used, as a date.
+/// A recurrence using values we don't know can't be described, so is left out.
+fn recurrence_properties(lines: &mut Vec<String>, recurrence: &PatternedRecurrence, due: Option<&IcalTime>) {
+    let (start_date, end_date, count) = match &recurrence.range {
+        RecurrenceRange::EndDate { start_date, end_date, .. } => (start_date, Some(end_date), None),
+        RecurrenceRange::NoEnd { start_date, .. } => (start_date, None, None),
+        RecurrenceRange::Numbered { start_date, number_of_occurrences, .. } => (start_date, None, Some(number_of_occurrences)),
+        RecurrenceRange::Unknown(_) => return,
+    };
+
+    let mut rule = match pattern_rule(&recurrence.pattern) {
+        Some(rule) => rule,
+        None => return,
+    };
+
+    match due {
+        Some(due) => lines.push(due.property("DTSTART")),
+        None => {
+            if let Some(start) = start_date.date() {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+            }
+        },
+    }
+
+    if let Some(end) = end_date.and_then(Date::date) {
+        let until = match due {
+            Some(due) => due.until(end),
+            None => end.format("%Y%m%d").to_string(),
+        };
+
+        rule.push_str(&format!(";UNTIL={}", until));
+    }
+
+    if let Some(count) = count {
+        rule.push_str(&format!(";COUNT={}", count));
+    }
+
+    lines.push(format!("RRULE:{}", rule));
+}
+
+/// The frequency part of an `RRULE`, eg: `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;WKST=SU`.
+/// `None` if the pattern, or any day or week within it, isn't one we know.
+fn pattern_rule(pattern: &RecurrencePattern) -> Option<String> {
+    let rule = match pattern {
+        RecurrencePattern::Daily { interval } => format!("FREQ=DAILY;INTERVAL={}", interval),
+        RecurrencePattern::Weekly { interval, days_of_week, first_day_of_week } => format!(
+            "FREQ=WEEKLY;INTERVAL={};BYDAY={};WKST={}",
+            interval, day_codes(days_of_week)?, day_code(first_day_of_week)?
+        ),
+        RecurrencePattern::AbsoluteMonthly { interval, day_of_month } => {
+            format!("FREQ=MONTHLY;INTERVAL={};BYMONTHDAY={}", interval, day_of_month)
+        },
+        RecurrencePattern::RelativeMonthly { interval, days_of_week, index } => format!(
+            "FREQ=MONTHLY;INTERVAL={};BYDAY={};BYSETPOS={}",
+            interval, day_codes(days_of_week)?, set_position(index)?
+        ),
+        RecurrencePattern::AbsoluteYearly { interval, day_of_month, month } => {
+            format!("FREQ=YEARLY;INTERVAL={};BYMONTH={};BYMONTHDAY={}", interval, month, day_of_month)
+        },
+        RecurrencePattern::RelativeYearly { interval, days_of_week, month, index } => format!(
+            "FREQ=YEARLY;INTERVAL={};BYMONTH={};BYDAY={};BYSETPOS={}",
+            interval, month, day_codes(days_of_week)?, set_position(index)?
+        ),
+        RecurrencePattern::Unknown(_) => return None,
+    };
+
+    Some(rule)
+}
+
+fn day_code(day: &DayOfWeek) -> Option<&'static str> {
+    Some(match day {
+        DayOfWeek::Sunday => "SU",
+        DayOfWeek::Monday => "MO",
+        DayOfWeek::Tuesday => "TU",
+        DayOfWeek::Wednesday => "WE",
+        DayOfWeek::Thursday => "TH",
+        DayOfWeek::Friday => "FR",
+        DayOfWeek::Saturday => "SA",
+        DayOfWeek::Unknown(_) => return None,
+    })
+}
+
+fn day_codes(days: &[DayOfWeek]) -> Option<String> {
+    Some(days.iter().map(day_code).collect::<Option<Vec<_>>>()?.join(","))
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Sun => "SU",
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+    }
+}
+
+/// Graph picks the nth of the matching days in the month; `BYSETPOS` does the same.
+fn set_position(index: &WeekIndex) -> Option<i32> {
+    Some(match index {
+        WeekIndex::First => 1,
+        WeekIndex::Second => 2,
+        WeekIndex::Third => 3,
+        WeekIndex::Fourth => 4,
+        WeekIndex::Last => -1,
+        WeekIndex::Unknown(_) => return None,
+    })
+}
+
+/// 1 is the highest priority, and 9 the lowest. An importance we don't know is treated as normal.
+fn priority(importance: &Importance) -> u8 {
+    match importance {
+        Importance::High => 1,
+        Importance::Normal | Importance::Unknown(_) => 5,
+        Importance::Low => 9,
+    }
+}
+
+/// iCalendar has no equivalent of waiting on others, or deferred, so they're the closest of the remaining statuses.
+fn status(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::NotStarted | TaskStatus::Deferred | TaskStatus::Unknown(_) => "NEEDS-ACTION",
+        TaskStatus::InProgress | TaskStatus::WaitingOnOthers => "IN-PROCESS",
+        TaskStatus::Completed => "COMPLETED",
+    }
+}
+
+/// A `DateTimeTimeZone` in UTC; times in a zone we don't know are assumed to already be in UTC.
+fn utc(value: &DateTimeTimeZone) -> Option<NaiveDateTime> {
+    value.utc().map(|date| date.naive_utc()).or_else(|| value.local())
+}
+
+/// A `DateTimeOffset`, eg: "2020-01-01T00:00:00Z", in UTC.
+fn parse_offset(value: &DateTimeOffset) -> Option<NaiveDateTime> {
+    value.utc().map(|date| date.naive_utc())
+}
+
+fn format_local(time: &NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn format_utc(time: &NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Eg: "-0800", or "+0530".
+fn format_offset(minutes: i32) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+
+    format!("{}{:02}{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+}
+
+/// Escape the characters which have a meaning within a `TEXT` value.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {},
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Parameter values containing a colon, semicolon or comma must be quoted.
+fn param_value(value: &str) -> String {
+    if value.contains([':', ';', ',']) {
+        format!("\"{}\"", value.replace('"', ""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split a line longer than 75 bytes into several, each continuation starting with a space.
+/// Lines are only split between characters, never within one.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::tasks::fixtures::{self, task};
+    use crate::backup::ListBackup;
+
+    /// The whole calendar, with the `tasks` in a list named "Home", exported at noon on 1st June 2021.
+    fn calendar(tasks: Vec<TodoTask>) -> String {
+        let list = fixtures::list("Home");
+
+        let mut backup = Backup::new(None);
+        backup.exported_at = "2021-06-01T12:00:00Z".to_string().into();
+        backup.lists.push(ListBackup { list, tasks, delta_link: None });
+
+        let mut output = Vec::new();
+        write(&mut output, &backup).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Each of the calendar's components of the given `kind`, eg: `VTODO`, with its lines joined by newlines.
+    fn components(calendar: &str, kind: &str) -> Vec<String> {
+        let (begin, end) = (format!("BEGIN:{}\r\n", kind), format!("END:{}\r\n", kind));
+
+        calendar.match_indices(&begin)
+            .map(|(start, _)| {
+                let length = calendar[start..].find(&end).unwrap() + end.len();
+                calendar[start..start + length].replace("\r\n", "\n")
+            })
+            .collect()
+    }
+
+    /// The `RRULE`, and the `DTSTART` it starts from, of a task due at the given time, with the given `recurrence`.
+    fn recurrence(due: Option<serde_json::Value>, recurrence: serde_json::Value) -> Vec<String> {
+        let task = task(json!({ "dueDateTime": due, "recurrence": recurrence }));
+
+        components(&calendar(vec![task]), "VTODO")[0].lines()
+            .filter(|line| line.starts_with("DTSTART") || line.starts_with("RRULE"))
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn patterns() {
+        let patterns = vec![
+            (json!({ "type": "daily", "interval": 3 }), "FREQ=DAILY;INTERVAL=3"),
+            (
+                json!({ "type": "weekly", "interval": 2, "daysOfWeek": ["monday", "wednesday"], "firstDayOfWeek": "sunday" }),
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;WKST=SU",
+            ),
+            (json!({ "type": "absoluteMonthly", "interval": 1, "dayOfMonth": 15 }), "FREQ=MONTHLY;INTERVAL=1;BYMONTHDAY=15"),
+            (
+                json!({ "type": "relativeMonthly", "interval": 1, "daysOfWeek": ["friday"], "index": "last" }),
+                "FREQ=MONTHLY;INTERVAL=1;BYDAY=FR;BYSETPOS=-1",
+            ),
+            (
+                json!({ "type": "absoluteYearly", "interval": 1, "dayOfMonth": 29, "month": 2 }),
+                "FREQ=YEARLY;INTERVAL=1;BYMONTH=2;BYMONTHDAY=29",
+            ),
+            (
+                json!({ "type": "relativeYearly", "interval": 1, "daysOfWeek": ["thursday"], "month": 11, "index": "fourth" }),
+                "FREQ=YEARLY;INTERVAL=1;BYMONTH=11;BYDAY=TH;BYSETPOS=4",
+            ),
+        ];
+
+        for (pattern, expected) in patterns {
+            let pattern: RecurrencePattern = serde_json::from_value(pattern).unwrap();
+            assert_eq!(pattern_rule(&pattern).as_deref(), Some(expected));
+        }
+
+        // Patterns, and days, we don't know can't be described.
+        let unknown = vec![
+            json!({ "type": "fortnightly", "interval": 1 }),
+            json!({ "type": "weekly", "interval": 1, "daysOfWeek": ["someday"], "firstDayOfWeek": "sunday" }),
+            json!({ "type": "relativeMonthly", "interval": 1, "daysOfWeek": ["friday"], "index": "fifth" }),
+        ];
+
+        for pattern in unknown {
+            let pattern: RecurrencePattern = serde_json::from_value(pattern).unwrap();
+            assert_eq!(pattern_rule(&pattern), None);
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let daily = json!({ "type": "daily", "interval": 1 });
+        let due = json!({ "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "Pacific Standard Time" });
+
+        // The end of the last day, in UTC; 23:59:59 PDT on 30th June.
+        let end_date = json!({ "type": "endDate", "startDate": "2021-05-03", "endDate": "2021-06-30" });
+        assert_eq!(recurrence(Some(due.clone()), json!({ "pattern": daily, "range": end_date })), vec![
+            "DTSTART;TZID=America/Los_Angeles:20210503T090000",
+            "RRULE:FREQ=DAILY;INTERVAL=1;UNTIL=20210701T065959Z",
+        ]);
+
+        // Without a due date, the recurrence is of whole days, from the start of the range.
+        assert_eq!(recurrence(None, json!({ "pattern": daily, "range": end_date })), vec![
+            "DTSTART;VALUE=DATE:20210503",
+            "RRULE:FREQ=DAILY;INTERVAL=1;UNTIL=20210630",
+        ]);
+
+        // Floating times end at the end of the day, wherever that is.
+        let floating = json!({ "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "Nowhere Standard Time" });
+        assert_eq!(recurrence(Some(floating), json!({ "pattern": daily, "range": end_date })), vec![
+            "DTSTART:20210503T090000",
+            "RRULE:FREQ=DAILY;INTERVAL=1;UNTIL=20210630T235959",
+        ]);
+
+        let no_end = json!({ "type": "noEnd", "startDate": "2021-05-03" });
+        assert_eq!(recurrence(Some(due.clone()), json!({ "pattern": daily, "range": no_end })), vec![
+            "DTSTART;TZID=America/Los_Angeles:20210503T090000",
+            "RRULE:FREQ=DAILY;INTERVAL=1",
+        ]);
+
+        let numbered = json!({ "type": "numbered", "startDate": "2021-05-03", "numberOfOccurrences": 10 });
+        assert_eq!(recurrence(Some(due.clone()), json!({ "pattern": daily, "range": numbered })), vec![
+            "DTSTART;TZID=America/Los_Angeles:20210503T090000",
+            "RRULE:FREQ=DAILY;INTERVAL=1;COUNT=10",
+        ]);
+
+        // A recurrence we can't describe is left out entirely; the task is only due once.
+        let unknown = json!({ "type": "someday", "startDate": "2021-05-03" });
+        assert!(recurrence(Some(due), json!({ "pattern": daily, "range": unknown })).is_empty());
+    }
+
+    #[test]
+    fn tasks() {
+        let task = task(json!({
+            "title": "Pay rent; call landlord, again",
+            "body": { "content": "Ask about\r\nthe boiler", "contentType": "text" },
+            "categories": ["Bills"],
+            "importance": "high",
+            "status": "completed",
+            "completedDateTime": { "dateTime": "2021-05-03T00:00:00.0000000", "timeZone": "UTC" },
+            "dueDateTime": { "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "Pacific Standard Time" },
+            "isReminderOn": true,
+            "reminderDateTime": { "dateTime": "2021-05-03T16:00:00.0000000", "timeZone": "UTC" },
+            "recurrence": {
+                "pattern": { "type": "weekly", "interval": 1, "daysOfWeek": ["monday"], "firstDayOfWeek": "sunday" },
+                "range": { "type": "noEnd", "startDate": "2021-05-03" },
+            },
+            "checklistItems": [{
+                "id": "AAMkAGI3",
+                "displayName": "Set up a standing order",
+                "isChecked": true,
+                "createdDateTime": "2021-03-01T10:05:00Z",
+                "checkedDateTime": "2021-03-04T08:00:00Z",
+            }],
+        }));
+
+        let calendar = calendar(vec![task]);
+        let todos = components(&calendar, "VTODO");
+
+        assert_eq!(todos[0], [
+            "BEGIN:VTODO",
+            "UID:AAMkAGI1",
+            "DTSTAMP:20210601T120000Z",
+            "CREATED:20210301T100000Z",
+            "LAST-MODIFIED:20210301T100000Z",
+            r"SUMMARY:Pay rent\; call landlord\, again",
+            r"DESCRIPTION:Ask about\nthe boiler",
+            "CATEGORIES:Home,Bills",
+            "PRIORITY:1",
+            "STATUS:COMPLETED",
+            "DTSTART;TZID=America/Los_Angeles:20210503T090000",
+            "RRULE:FREQ=WEEKLY;INTERVAL=1;BYDAY=MO;WKST=SU",
+            "DUE;TZID=America/Los_Angeles:20210503T090000",
+            "COMPLETED:20210503T000000Z",
+            "BEGIN:VALARM",
+            "ACTION:DISPLAY",
+            r"DESCRIPTION:Pay rent\; call landlord\, again",
+            "TRIGGER;VALUE=DATE-TIME:20210503T160000Z",
+            "END:VALARM",
+            "END:VTODO",
+            "",
+        ].join("\n"));
+
+        assert_eq!(todos[1], [
+            "BEGIN:VTODO",
+            "UID:AAMkAGI3",
+            "DTSTAMP:20210601T120000Z",
+            "CREATED:20210301T100500Z",
+            "SUMMARY:Set up a standing order",
+            "CATEGORIES:Home",
+            "RELATED-TO;RELTYPE=PARENT:AAMkAGI1",
+            "STATUS:COMPLETED",
+            "COMPLETED:20210304T080000Z",
+            "END:VTODO",
+            "",
+        ].join("\n"));
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(calendar.ends_with("END:VTODO\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn regular_zones_repeat_yearly() {
+        let due = json!({ "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "Pacific Standard Time" });
+        let calendar = calendar(vec![task(json!({ "dueDateTime": due }))]);
+
+        assert_eq!(components(&calendar, "VTIMEZONE"), vec![[
+            "BEGIN:VTIMEZONE",
+            "TZID:America/Los_Angeles",
+            "X-LIC-LOCATION:America/Los_Angeles",
+            "BEGIN:STANDARD",
+            "DTSTART:20210101T000000",
+            "TZOFFSETFROM:-0800",
+            "TZOFFSETTO:-0800",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20210314T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+            "TZOFFSETFROM:-0800",
+            "TZOFFSETTO:-0700",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20211107T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+            "TZOFFSETFROM:-0700",
+            "TZOFFSETTO:-0800",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+            "",
+        ].join("\n")]);
+    }
+
+    #[test]
+    fn changes_before_the_current_rules_are_written_individually() {
+        // The US moved daylight saving time to March and November in 2007.
+        let tasks = vec![
+            task(json!({ "dueDateTime": { "dateTime": "2005-05-03T09:00:00.0000000", "timeZone": "Pacific Standard Time" } })),
+            task(json!({ "startDateTime": { "dateTime": "2007-05-03T09:00:00.0000000", "timeZone": "Pacific Standard Time" } })),
+        ];
+
+        assert_eq!(components(&calendar(tasks), "VTIMEZONE"), vec![[
+            "BEGIN:VTIMEZONE",
+            "TZID:America/Los_Angeles",
+            "X-LIC-LOCATION:America/Los_Angeles",
+            "BEGIN:STANDARD",
+            "DTSTART:20050101T000000",
+            "TZOFFSETFROM:-0800",
+            "TZOFFSETTO:-0800",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20050403T020000",
+            "TZOFFSETFROM:-0800",
+            "TZOFFSETTO:-0700",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20051030T020000",
+            "TZOFFSETFROM:-0700",
+            "TZOFFSETTO:-0800",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20060402T020000",
+            "TZOFFSETFROM:-0800",
+            "TZOFFSETTO:-0700",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20061029T020000",
+            "TZOFFSETFROM:-0700",
+            "TZOFFSETTO:-0800",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20070311T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+            "TZOFFSETFROM:-0800",
+            "TZOFFSETTO:-0700",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20071104T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+            "TZOFFSETFROM:-0700",
+            "TZOFFSETTO:-0800",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+            "",
+        ].join("\n")]);
+    }
+
+    #[test]
+    fn zones_without_changes_have_a_single_observance() {
+        let due = json!({ "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "Tokyo Standard Time" });
+        let tokyo = calendar(vec![task(json!({ "dueDateTime": due }))]);
+
+        assert_eq!(components(&tokyo, "VTIMEZONE"), vec![[
+            "BEGIN:VTIMEZONE",
+            "TZID:Asia/Tokyo",
+            "X-LIC-LOCATION:Asia/Tokyo",
+            "BEGIN:STANDARD",
+            "DTSTART:20210101T000000",
+            "TZOFFSETFROM:+0900",
+            "TZOFFSETTO:+0900",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+            "",
+        ].join("\n")]);
+
+        // Times in UTC, or in zones we don't know, don't need one.
+        let tasks = vec![
+            task(json!({ "dueDateTime": { "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "UTC" } })),
+            task(json!({ "dueDateTime": { "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "Nowhere Standard Time" } })),
+        ];
+        assert!(components(&calendar(tasks), "VTIMEZONE").is_empty());
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape_text("a\\b;c,d\r\ne:f\"g"), r#"a\\b\;c\,d\ne:f"g"#);
+        assert_eq!(param_value("America/Los_Angeles"), "America/Los_Angeles");
+        assert_eq!(param_value("Etc/GMT+5;\"odd\""), "\"Etc/GMT+5;odd\"");
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let short = "SUMMARY:".to_string() + &"a".repeat(MAX_LINE_LENGTH - 8);
+        assert_eq!(fold(&short), short);
+
+        let long = "SUMMARY:".to_string() + &"a".repeat(100);
+        assert_eq!(fold(&long), format!("{}\r\n {}", &long[..75], &long[75..]));
+
+        // Characters aren't split across lines, however many bytes they take.
+        let wide = "SUMMARY:".to_string() + &"é".repeat(100);
+        let folded = fold(&wide);
+
+        for line in folded.split("\r\n") {
+            assert!(line.len() <= MAX_LINE_LENGTH, "{:?}", line);
+        }
+        assert_eq!(folded.split("\r\n").next().unwrap().len(), 74);
+        assert_eq!(folded.replace("\r\n ", ""), wide);
+
+        // Folded lines are written to the calendar.
+        let calendar = calendar(vec![task(json!({ "title": "a".repeat(100) }))]);
+        assert!(calendar.contains(&format!("SUMMARY:{}\r\n {}\r\n", "a".repeat(67), "a".repeat(33))));
+    }
+}
@@ -0,0 +1,57 @@
+//! The JSON backup format; the `Backup` itself, serialized with every property of every list and task.
+
+use std::io::{self, Read, Write};
+
+use crate::backup::{Backup, BACKUP_FORMAT_VERSION};
+use crate::error::{Error, Result};
+
+/// Write the complete `backup` as a JSON document. 
+/// This is the only format which preserves every property of every list and task. 
+pub fn write<W: Write>(output: &mut W, backup: &Backup) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *output, backup).map_err(io::Error::from)?;
+    writeln!(output)?;
+
+    Ok(())
+}
+
+/// Read a backup written by `write`. 
+/// 
+/// Backups written by a newer version of this program are rejected, as any properties it added would be dropped, 
+/// and lost if the backup was written again. The version is checked before anything else is read, 
+/// as the rest may not even be readable. 
+pub fn read<R: Read>(input: R) -> Result<Backup> {
+    let backup: serde_json::Value = serde_json::from_reader(input).map_err(io::Error::from)?;
+
+    match backup.get("version").and_then(serde_json::Value::as_u64) {
+        Some(version) if version > u64::from(BACKUP_FORMAT_VERSION) => Err(Error::UsageError(format!(
+            "The backup is version {}, but only versions up to {} are supported; try a newer version of this program",
+            version, BACKUP_FORMAT_VERSION
+        ))),
+        _ => Ok(serde_json::from_value(backup).map_err(io::Error::from)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut output = Vec::new();
+        write(&mut output, &Backup::new(None)).unwrap();
+
+        let backup = read(output.as_slice()).unwrap();
+        assert_eq!(backup.version, BACKUP_FORMAT_VERSION);
+        assert!(backup.lists.is_empty());
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let newer = format!(r#"{{"version":{},"exportedAt":"2021-06-01T12:00:00Z","user":null,"lists":[],"newProperty":[]}}"#, BACKUP_FORMAT_VERSION + 1);
+
+        match read(newer.as_bytes()) {
+            Err(Error::UsageError(message)) => assert!(message.contains("try a newer version"), "{}", message),
+            other => panic!("expected the backup to be rejected, got: {:?}", other.map(|backup| backup.version)),
+        }
+    }
+}
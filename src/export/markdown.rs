@@ -0,0 +1,210 @@
+//! Markdown checklists; a heading for each list, followed by a `- [ ]` item for each task.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::api::tasks::{BodyType, Importance, TaskStatus, TodoTask};
+use crate::backup::{Backup, ListBackup};
+use crate::error::Result;
+
+use super::html;
+
+/// Write every list in the `backup` into a single document.
+pub fn write<W: Write>(output: &mut W, backup: &Backup) -> Result<()> {
+    for (i, list) in backup.lists.iter().enumerate() {
+        if i > 0 {
+            writeln!(output)?;
+        }
+
+        write_list(output, list)?;
+    }
+
+    Ok(())
+}
+
+/// Write a single list as its own document.
+pub fn write_list<W: Write>(output: &mut W, list: &ListBackup) -> Result<()> {
+    writeln!(output, "# {}", escape(&list.list.display_name))?;
+    writeln!(output)?;
+
+    for task in &list.tasks {
+        write_task(output, task)?;
+    }
+
+    Ok(())
+}
+
+/// Eg: "- [ ] Buy milk `High` *due 2021-05-01*", followed by the task's steps, and its body, indented beneath it.
+fn write_task<W: Write>(output: &mut W, task: &TodoTask) -> Result<()> {
+    let checkbox = if matches!(task.status, TaskStatus::Completed) { "[x]" } else { "[ ]" };
+    let mut line = format!("- {} {}", checkbox, escape(&task.title));
+
+    match task.importance {
+        Importance::High => line.push_str(" `High`"),
+        Importance::Low => line.push_str(" `Low`"),
+        Importance::Normal | Importance::Unknown(_) => {},
+    }
+
+    match task.status {
+        TaskStatus::InProgress | TaskStatus::WaitingOnOthers | TaskStatus::Deferred | TaskStatus::Unknown(_) => line.push_str(&format!(" `{}`", task.status)),
+        TaskStatus::NotStarted | TaskStatus::Completed => {},
+    }
+
+    if let Some(due) = &task.due_date_time {
+        // Due dates are whole days, so the time isn't interesting.
+        match due.local() {
+            Some(local) => line.push_str(&format!(" *due {}*", local.date())),
+            None => line.push_str(&format!(" *due {}*", due.date_time())),
+        }
+    }
+
+    writeln!(output, "{}", line)?;
+
+    for item in &task.checklist_items {
+        writeln!(output, "  - {} {}", if item.is_checked { "[x]" } else { "[ ]" }, escape(&item.display_name))?;
+    }
+
+    let body = match task.body.content_type {
+        BodyType::Html => html::to_markdown(&task.body.content),
+        BodyType::Text | BodyType::Unknown(_) => escape(task.body.content.trim()),
+    };
+
+    if !body.is_empty() {
+        writeln!(output)?;
+
+        for body_line in body.lines() {
+            if body_line.is_empty() {
+                writeln!(output)?;
+            } else {
+                writeln!(output, "  {}", body_line)?;
+            }
+        }
+
+        writeln!(output)?;
+    }
+
+    Ok(())
+}
+
+/// Escape the characters which would otherwise be read as Markdown formatting.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// A unique file name for each of the lists of the `backup`, in order, eg: "Groceries.md".
+///
+/// Characters which aren't safe in file names are replaced, and lists with the same name are numbered.
+pub fn file_names(backup: &Backup) -> Vec<String> {
+    let mut used = HashSet::new();
+
+    backup.lists.iter()
+        .map(|list| {
+            let stem: String = list.list.display_name.trim().chars()
+                .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') { c } else { '_' })
+                .collect();
+
+            let stem = match stem.trim_matches('.') {
+                "" => "list".to_string(),
+                stem => stem.to_string(),
+            };
+
+            let mut name = format!("{}.md", stem);
+            let mut n = 2;
+
+            while !used.insert(name.to_lowercase()) {
+                name = format!("{} ({}).md", stem, n);
+                n += 1;
+            }
+
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::tasks::fixtures::{list, task};
+
+    fn backup(lists: &[&str]) -> Backup {
+        let mut backup = Backup::new(None);
+
+        for name in lists {
+            backup.lists.push(ListBackup { list: list(name), tasks: Vec::new(), delta_link: None });
+        }
+
+        backup
+    }
+
+    #[test]
+    fn lists_are_checklists() {
+        let mut backup = backup(&["Work_stuff", "Home"]);
+
+        backup.lists[0].tasks.push(task(json!({
+            "title": "Fix [bug] #12 *now*",
+            "importance": "high",
+            "status": "inProgress",
+            "dueDateTime": { "dateTime": "2021-05-01T00:00:00.0000000", "timeZone": "UTC" },
+            "body": { "content": "<html><head><style>p { margin: 0 }</style></head><body><p>See the <a href=\"https://example.com/log\">log</a></p><p>Then:<br>retry</p></body></html>", "contentType": "html" },
+            "checklistItems": [
+                { "id": "1", "displayName": "Reproduce_it", "isChecked": true, "createdDateTime": "2021-03-01T10:00:00Z" },
+                { "id": "2", "displayName": "Write a test", "isChecked": false, "createdDateTime": "2021-03-01T10:00:00Z" },
+            ],
+        })));
+        backup.lists[0].tasks.push(task(json!({ "title": "Ship it", "importance": "low", "status": "completed" })));
+        backup.lists[1].tasks.push(task(json!({ "title": "Water <plants>", "body": { "content": "  Every *other* day \n", "contentType": "text" } })));
+
+        let mut output = Vec::new();
+        write(&mut output, &backup).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), [
+            r"# Work\_stuff",
+            "",
+            r"- [ ] Fix \[bug\] \#12 \*now\* `High` `In progress` *due 2021-05-01*",
+            r"  - [x] Reproduce\_it",
+            "  - [ ] Write a test",
+            "",
+            "  See the [log](https://example.com/log)",
+            "",
+            r"  Then:\",
+            "  retry",
+            "",
+            "- [x] Ship it `Low`",
+            "",
+            "# Home",
+            "",
+            r"- [ ] Water \<plants\>",
+            "",
+            r"  Every \*other\* day",
+            "",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn file_names_are_safe_and_unique() {
+        let backup = backup(&["Groceries", "groceries", "Groceries", "Work/Home: 2021?", "..", "  ", "Café.notes"]);
+
+        assert_eq!(file_names(&backup), vec![
+            "Groceries.md",
+            "groceries (2).md",
+            "Groceries (3).md",
+            "Work_Home_ 2021_.md",
+            "list.md",
+            "list (2).md",
+            "Café.notes.md",
+        ]);
+    }
+}
@@ -0,0 +1,19 @@
+//! Writers for each of the formats a `Backup` may be exported as, and readers for those it may be restored from. 
+
+use crate::api::tasks::{BodyType, ItemBody};
+
+pub mod csv;
+pub mod html;
+pub mod ical;
+pub mod json;
+pub mod markdown;
+pub mod text;
+pub mod todotxt;
+
+/// The content of a task's `body` as plain text. 
+pub fn body_text(body: &ItemBody) -> String {
+    match body.content_type {
+        BodyType::Text | BodyType::Unknown(_) => body.content.trim().to_string(),
+        BodyType::Html => html::to_text(&body.content),
+    }
+}
@@ -0,0 +1,58 @@
+//! Plain text; the title of each task, one per line.
+
+use std::io::Write;
+
+use crate::api::tasks::{TodoTaskList, WellknownListName};
+use crate::backup::Backup;
+use crate::error::Result;
+
+/// Write the title of each task, one per line, followed by its steps, indented beneath it. 
+/// If the backup contains more than one list, each list's tasks are preceded by its name. 
+pub fn write<W: Write>(output: &mut W, backup: &Backup) -> Result<()> {
+    let with_headings = backup.lists.len() > 1;
+
+    for list in &backup.lists {
+        if with_headings {
+            writeln!(output, "{}", heading(&list.list))?;
+        }
+
+        for task in &list.tasks {
+            writeln!(output, "{}", task.title)?;
+
+            for item in &task.checklist_items {
+                writeln!(output, "  [{}] {}", if item.is_checked { "x" } else { " " }, item.display_name)?;
+            }
+        }
+
+        if with_headings {
+            writeln!(output)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The name of the list, followed by whether it's a well-known list, shared, or owned by someone else. 
+fn heading(list: &TodoTaskList) -> String {
+    let mut notes = Vec::new();
+
+    match list.wellknown_list_name {
+        WellknownListName::DefaultList => notes.push("default list"),
+        WellknownListName::FlaggedEmails => notes.push("flagged emails"),
+        WellknownListName::None | WellknownListName::UnknownFutureValue | WellknownListName::Unknown(_) => {},
+    }
+
+    if list.is_shared {
+        notes.push("shared");
+    }
+
+    if !list.is_owner {
+        notes.push("not owned by you");
+    }
+
+    if notes.is_empty() {
+        format!("{}:", list.display_name)
+    } else {
+        format!("{} [{}]:", list.display_name, notes.join(", "))
+    }
+}
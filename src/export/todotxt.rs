@@ -0,0 +1,528 @@
+//! The todo.txt format; one task per line, with its priority, dates, and list as a project.
+//!
+//! Importance is mapped to priorities; `High` is `(A)`, `Low` is `(C)`, and `Normal` has no priority.
+//! todo.txt projects can't contain spaces, so spaces in list names are written as underscores,
+//! and read back as spaces, while literal underscores and backslashes are escaped with a backslash, eg: `+to\_do`.
+//! Categories are written as contexts, in the same way.
+//!
+//! todo.txt has no notion of sub-tasks, so the steps of a task are written as their own lines, following it.
+//! A task with steps is given an `id:` tag, and each of its steps a `p:` tag with the same value.
+//!
+//! Words of a title which would otherwise be read as a project, context or tag, eg: "+launch" or "due:friday",
+//! are escaped with a backslash, eg: `\+launch`, as are those which would be read as a completion mark, priority
+//! or date at the start of the title. The list is the last project on a line, which is where it's written.
+//!
+//! See: <https://github.com/todotxt/todo.txt>
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use chrono::NaiveDate;
+
+use crate::api::{DateTimeOffset, DateTimeTimeZone};
+use crate::api::tasks::{BodyType, ChecklistItem, Importance, ItemBody, TaskStatus, TodoTask, TodoTaskList, WellknownListName};
+use crate::backup::{Backup, ListBackup};
+use crate::error::Result;
+
+/// The name given to the list of tasks read without a project.
+const DEFAULT_LIST_NAME: &str = "Tasks";
+
+/// Write each task as a line of todo.txt.
+pub fn write<W: Write>(output: &mut W, backup: &Backup) -> Result<()> {
+    let mut next_id = 1;
+
+    for list in &backup.lists {
+        for task in &list.tasks {
+            let mut line = line(&list.list, task);
+
+            if task.checklist_items.is_empty() {
+                writeln!(output, "{}", line)?;
+                continue;
+            }
+
+            line.push_str(&format!(" id:{}", next_id));
+            writeln!(output, "{}", line)?;
+
+            for item in &task.checklist_items {
+                writeln!(output, "{}", item_line(&list.list, item, next_id))?;
+            }
+
+            next_id += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Eg: "x 2021-04-02 2021-03-01 Buy milk +Groceries @Red_category due:2021-04-01 pri:A".
+fn line(list: &TodoTaskList, task: &TodoTask) -> String {
+    let mut parts = Vec::new();
+    let completed = matches!(task.status, TaskStatus::Completed);
+
+    if completed {
+        parts.push("x".to_string());
+
+        // A completion date may only be given alongside a creation date.
+        if let Some(date) = task.completed_date_time.as_ref().and_then(date_part) {
+            if created_date(task).is_some() {
+                parts.push(date.to_string());
+            }
+        }
+    } else if let Some(priority) = priority(&task.importance) {
+        parts.push(format!("({})", priority));
+    }
+
+    if let Some(date) = created_date(task) {
+        parts.push(date.to_string());
+    }
+
+    parts.push(escape_title(&task.title));
+    parts.push(format!("+{}", project(&list.display_name)));
+
+    for category in &task.categories {
+        parts.push(format!("@{}", project(category)));
+    }
+
+    if let Some(date) = task.due_date_time.as_ref().and_then(date_part) {
+        parts.push(format!("due:{}", date));
+    }
+
+    // Completed tasks can't have a priority, so it's kept as a tag instead.
+    if completed {
+        if let Some(priority) = priority(&task.importance) {
+            parts.push(format!("pri:{}", priority));
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Eg: "x 2021-04-02 2021-03-01 Buy eggs +Groceries p:1".
+fn item_line(list: &TodoTaskList, item: &ChecklistItem, parent: usize) -> String {
+    let mut parts = Vec::new();
+    let created = utc_date(&item.created_date_time);
+
+    if item.is_checked {
+        parts.push("x".to_string());
+
+        if let (Some(checked), Some(_)) = (item.checked_date_time.as_ref().and_then(utc_date), created) {
+            parts.push(checked.to_string());
+        }
+    }
+
+    if let Some(created) = created {
+        parts.push(created.to_string());
+    }
+
+    parts.push(escape_title(&item.display_name));
+    parts.push(format!("+{}", project(&list.display_name)));
+    parts.push(format!("p:{}", parent));
+
+    parts.join(" ")
+}
+
+/// The tags which are read from a line, eg: "due:2021-04-01".
+const TAG_KEYS: &[&str] = &["due", "pri", "id", "p"];
+
+/// The words of a `title`, separated by single spaces, with any which would be read as something else escaped.
+fn escape_title(title: &str) -> String {
+    title.split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            let is_tag = word.starts_with('\\')
+                || (word.len() > 1 && (word.starts_with('+') || word.starts_with('@')))
+                || word.split_once(':').is_some_and(|(key, value)| !value.is_empty() && TAG_KEYS.contains(&key));
+
+            // The first word follows the completion mark, priority and dates, so may be mistaken for one of them.
+            let is_prefix = i == 0 && (word == "x" || parse_priority(word).is_some() || parse_date(word).is_some());
+
+            if is_tag || is_prefix {
+                format!("\\{}", word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The priority of a word such as "(A)".
+fn parse_priority(word: &str) -> Option<char> {
+    match word.chars().collect::<Vec<_>>().as_slice() {
+        ['(', p, ')'] if p.is_ascii_uppercase() => Some(*p),
+        _ => None,
+    }
+}
+
+fn priority(importance: &Importance) -> Option<char> {
+    match importance {
+        Importance::High => Some('A'),
+        Importance::Normal | Importance::Unknown(_) => None,
+        Importance::Low => Some('C'),
+    }
+}
+
+fn importance(priority: char) -> Importance {
+    match priority {
+        'A' => Importance::High,
+        'B' => Importance::Normal,
+        _ => Importance::Low,
+    }
+}
+
+/// The name of a list as a project, which can't contain whitespace.
+/// Spaces are written as underscores, so underscores, and the backslashes which escape them, are escaped.
+fn project(list_name: &str) -> String {
+    list_name.split_whitespace()
+        .map(|word| word.replace('\\', "\\\\").replace('_', "\\_"))
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// The name of a list from a `project`, without its leading `+`, or a category from a context.
+fn parse_project(project: &str) -> String {
+    let mut name = String::new();
+    let mut chars = project.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => name.extend(chars.next()),
+            '_' => name.push(' '),
+            c => name.push(c),
+        }
+    }
+
+    name
+}
+
+fn created_date(task: &TodoTask) -> Option<NaiveDate> {
+    utc_date(&task.created_date_time)
+}
+
+/// The date of a date time, in its own zone, eg: "2021-04-01" of "2021-04-01T00:00:00.0000000".
+fn date_part(date_time: &DateTimeTimeZone) -> Option<NaiveDate> {
+    date_time.local().map(|local| local.date())
+}
+
+fn utc_date(date_time: &DateTimeOffset) -> Option<NaiveDate> {
+    date_time.utc().map(|utc| utc.naive_utc().date())
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Read a todo.txt file into a `Backup`, which may then be restored.
+///
+/// Each task is placed in a list named after its last project; tasks without a project are placed in the default list.
+/// Tasks and lists are given IDs made up from their line number and name, as they have none of their own.
+pub fn read<R: BufRead>(input: R) -> Result<Backup> {
+    let mut backup = Backup::new(None);
+
+    // The list, and position within it, of each task with an `id:` tag.
+    let mut parents: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line = parse_line(&line, number + 1, &backup.exported_at);
+
+        if let Some(&(list, task)) = line.parent.as_ref().and_then(|parent| parents.get(parent)) {
+            backup.lists[list].tasks[task].checklist_items.push(ChecklistItem {
+                display_name: line.task.title,
+                id: line.task.id,
+                is_checked: matches!(line.task.status, TaskStatus::Completed),
+                created_date_time: line.task.created_date_time,
+                checked_date_time: line.completed_date.map(midnight_utc),
+            });
+
+            continue;
+        }
+
+        let index = match backup.lists.iter().position(|list| list.list.display_name == line.list_name) {
+            Some(index) => index,
+            None => {
+                backup.lists.push(ListBackup { list: new_list(&line.list_name), tasks: Vec::new(), delta_link: None });
+                backup.lists.len() - 1
+            },
+        };
+
+        if let Some(id) = line.id {
+            parents.insert(id, (index, backup.lists[index].tasks.len()));
+        }
+
+        backup.lists[index].tasks.push(line.task);
+    }
+
+    Ok(backup)
+}
+
+/// A single line of a todo.txt file.
+struct Line {
+    /// The list the task belongs in.
+    list_name: String,
+
+    task: TodoTask,
+
+    completed_date: Option<NaiveDate>,
+
+    /// The value of the `id:` tag, identifying the task to its steps.
+    id: Option<String>,
+
+    /// The value of the `p:` tag, if the line is a step of another task.
+    parent: Option<String>,
+}
+
+fn new_list(name: &str) -> TodoTaskList {
+    TodoTaskList {
+        display_name: name.to_string(),
+        id: format!("todotxt:{}", name),
+        is_owner: true,
+        is_shared: false,
+        wellknown_list_name: if name == DEFAULT_LIST_NAME { WellknownListName::DefaultList } else { WellknownListName::None },
+        extensions: Vec::new(),
+        additional_properties: Default::default(),
+    }
+}
+
+/// Parse a single line into a task.
+/// Tasks without a creation date are treated as having been created at `now`.
+fn parse_line(line: &str, number: usize, now: &DateTimeOffset) -> Line {
+    let mut words = line.split_whitespace().peekable();
+
+    let completed = words.peek() == Some(&"x");
+    if completed {
+        words.next();
+    }
+
+    let mut priority = words.peek().and_then(|word| parse_priority(word));
+    if priority.is_some() {
+        words.next();
+    }
+
+    // Up to two dates; the completion date, then the creation date, for completed tasks.
+    let mut dates = Vec::new();
+    while dates.len() < if completed { 2 } else { 1 } {
+        match words.peek().and_then(|word| parse_date(word)) {
+            Some(date) => {
+                dates.push(date);
+                words.next();
+            },
+            None => break,
+        }
+    }
+
+    let (completed_date, created_date) = match dates.as_slice() {
+        [completion, creation] => (Some(*completion), Some(*creation)),
+        [creation] if !completed => (None, Some(*creation)),
+        [completion] => (Some(*completion), None),
+        _ => (None, None),
+    };
+
+    let words: Vec<&str> = words.collect();
+
+    // Other projects are left in the title, as it's only possible to place a task in one list.
+    let list_index = words.iter().rposition(|word| word.len() > 1 && word.starts_with('+'));
+
+    let mut list_name = None;
+    let mut due = None;
+    let mut id = None;
+    let mut parent = None;
+    let mut contexts = Vec::new();
+    let mut title = Vec::new();
+
+    for (i, word) in words.into_iter().enumerate() {
+        if let Some(escaped) = word.strip_prefix('\\') {
+            title.push(escaped);
+            continue;
+        }
+
+        if Some(i) == list_index {
+            list_name = Some(parse_project(&word[1..]));
+            continue;
+        }
+
+        if let Some(context) = word.strip_prefix('@').filter(|context| !context.is_empty()) {
+            contexts.push(parse_project(context));
+            continue;
+        }
+
+        if let Some(date) = word.strip_prefix("due:").and_then(parse_date) {
+            due = Some(date);
+            continue;
+        }
+
+        if let Some(p) = word.strip_prefix("pri:").and_then(|p| p.chars().next()) {
+            priority = Some(p.to_ascii_uppercase());
+            continue;
+        }
+
+        if let Some(value) = word.strip_prefix("id:").filter(|value| !value.is_empty()) {
+            id = Some(value.to_string());
+            continue;
+        }
+
+        if let Some(value) = word.strip_prefix("p:").filter(|value| !value.is_empty()) {
+            parent = Some(value.to_string());
+            continue;
+        }
+
+        title.push(word);
+    }
+
+    let created = created_date.map_or_else(|| now.clone(), midnight_utc);
+
+    let task = TodoTask {
+        body: ItemBody { content: String::new(), content_type: BodyType::Text },
+        body_last_modified_date_time: None,
+        categories: contexts,
+        completed_date_time: completed_date.map(start_of_day),
+        created_date_time: created.clone(),
+        due_date_time: due.map(start_of_day),
+        has_attachments: false,
+        id: format!("todotxt:{}", number),
+        importance: priority.map_or(Importance::Normal, importance),
+        is_reminder_on: false,
+        last_modified_date_time: created,
+        recurrence: None,
+        reminder_date_time: None,
+        start_date_time: None,
+        status: if completed { TaskStatus::Completed } else { TaskStatus::NotStarted },
+        title: title.join(" "),
+        checklist_items: Vec::new(),
+        linked_resources: Vec::new(),
+        extensions: Vec::new(),
+        attachments: Vec::new(),
+        additional_properties: Default::default(),
+    };
+
+    Line {
+        list_name: list_name.unwrap_or_else(|| DEFAULT_LIST_NAME.to_string()),
+        task,
+        completed_date,
+        id,
+        parent,
+    }
+}
+
+/// Midnight, UTC, at the start of the `date`, eg: "2021-04-01T00:00:00Z".
+fn midnight_utc(date: NaiveDate) -> DateTimeOffset {
+    format!("{}T00:00:00Z", date).into()
+}
+
+fn start_of_day(date: NaiveDate) -> DateTimeTimeZone {
+    DateTimeTimeZone::new(format!("{}T00:00:00.0000000", date), "UTC")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::tasks::fixtures::task;
+
+    /// Write the `tasks` to todo.txt, in a list named `list`, and read them back.
+    fn round_trip(list: &str, tasks: Vec<TodoTask>) -> (String, Backup) {
+        let mut backup = Backup::new(None);
+        backup.lists.push(ListBackup { list: new_list(list), tasks, delta_link: None });
+
+        let mut output = Vec::new();
+        write(&mut output, &backup).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        let backup = read(text.as_bytes()).unwrap();
+
+        (text, backup)
+    }
+
+    #[test]
+    fn projects_in_the_title_are_kept_in_the_title() {
+        let (text, backup) = round_trip("Work", vec![task(json!({ "title": "Plan +launch party" }))]);
+
+        assert_eq!(text, "2021-03-01 Plan \\+launch party +Work\n");
+        assert_eq!(backup.lists.len(), 1);
+        assert_eq!(backup.lists[0].list.display_name, "Work");
+        assert_eq!(backup.lists[0].tasks[0].title, "Plan +launch party");
+    }
+
+    #[test]
+    fn tag_shaped_words_are_kept_in_the_title() {
+        let titles = [
+            "Email @bob about due:2021-01-01",
+            "Renumber id:5 and p:3",
+            "Ask about pri:A",
+            "x marks the spot",
+            "(A) is for apple",
+            "2021-05-05 is the deadline",
+            "Back up C:\\ and \\\\server",
+        ];
+
+        for title in &titles {
+            let (text, backup) = round_trip("Home", vec![task(json!({ "title": title, "categories": ["Red category"] }))]);
+            let read = &backup.lists[0].tasks[0];
+
+            assert_eq!(backup.lists[0].list.display_name, "Home", "{}", text);
+            assert_eq!(&read.title, title, "{}", text);
+            assert_eq!(read.categories, vec!["Red category"], "{}", text);
+            assert!(read.due_date_time.is_none(), "{}", text);
+            assert!(matches!(read.status, TaskStatus::NotStarted), "{}", text);
+            assert!(matches!(read.importance, Importance::Normal), "{}", text);
+        }
+    }
+
+    #[test]
+    fn tags_are_read_after_the_title() {
+        let completed = task(json!({
+            "title": "Buy +milk",
+            "status": "completed",
+            "importance": "high",
+            "completedDateTime": { "dateTime": "2021-04-02T00:00:00.0000000", "timeZone": "UTC" },
+            "dueDateTime": { "dateTime": "2021-04-01T00:00:00.0000000", "timeZone": "UTC" },
+        }));
+
+        let (text, backup) = round_trip("Groceries and more", vec![completed]);
+        let read = &backup.lists[0].tasks[0];
+
+        assert_eq!(text, "x 2021-04-02 2021-03-01 Buy \\+milk +Groceries_and_more due:2021-04-01 pri:A\n");
+        assert_eq!(backup.lists[0].list.display_name, "Groceries and more");
+        assert_eq!(read.title, "Buy +milk");
+        assert!(matches!(read.status, TaskStatus::Completed));
+        assert!(matches!(read.importance, Importance::High));
+        assert_eq!(read.due_date_time.as_ref().map(|due| due.date_time()), Some("2021-04-01T00:00:00.0000000"));
+    }
+
+    #[test]
+    fn underscores_in_projects_and_contexts_are_kept() {
+        let categorized = task(json!({ "categories": ["Red_category", "Blue category", "C:\\_temp"] }));
+
+        let (text, backup) = round_trip("to_do list", vec![categorized]);
+
+        assert_eq!(text, "2021-03-01 Buy milk +to\\_do_list @Red\\_category @Blue_category @C:\\\\\\_temp\n");
+        assert_eq!(backup.lists[0].list.display_name, "to_do list");
+        assert_eq!(backup.lists[0].tasks[0].categories, vec!["Red_category", "Blue category", "C:\\_temp"]);
+    }
+
+    #[test]
+    fn steps_are_read_back_into_their_task() {
+        let parent = task(json!({
+            "title": "Pack",
+            "checklistItems": [
+                { "id": "1", "displayName": "Pack +socks", "isChecked": true, "createdDateTime": "2021-03-01T10:00:00Z", "checkedDateTime": "2021-03-02T10:00:00Z" },
+                { "id": "2", "displayName": "p:1 shirts", "isChecked": false, "createdDateTime": "2021-03-01T10:00:00Z" },
+            ],
+        }));
+
+        let (text, backup) = round_trip("Trip", vec![parent]);
+        let tasks = &backup.lists[0].tasks;
+
+        assert_eq!(tasks.len(), 1, "{}", text);
+        assert_eq!(tasks[0].title, "Pack");
+
+        let items: Vec<(&str, bool)> = tasks[0].checklist_items.iter().map(|item| (item.display_name.as_str(), item.is_checked)).collect();
+        assert_eq!(items, vec![("Pack +socks", true), ("p:1 shirts", false)]);
+    }
+}
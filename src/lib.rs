@@ -0,0 +1,76 @@
+//! Read, back up and restore Microsoft To Do lists and tasks, using the Microsoft Graph API.
+//!
+//! This is the library behind the `microsoft-todo-export` command. It provides:
+//!
+//! - [`client::GraphClient`], which sends authenticated requests to Graph, renewing the access token,
+//!   and retrying throttled requests, and its asynchronous counterpart, [`client::AsyncGraphClient`].
+//! - The models of the Graph resources it reads, in [`api::tasks`] and [`api::user`].
+//! - A [`backup::Backup`] of a user's lists and tasks, and the [`export`]ers which write one in each format.
+//! - The parts of an export beyond reading each collection: fetching many items at once in a [`batch`],
+//!   fetching only what's changed with [`delta`] queries, and downloading [`attachments`], for several lists
+//!   at once with a [`pool`] of threads.
+//! - The [`restore::Restorer`], which recreates the lists and tasks of a backup in the signed in account.
+//! - The [`timezone`]s named in Graph's dates, and their rules.
+//!
+//! ```no_run
+//! use microsoft_todo_export::auth::Session;
+//! use microsoft_todo_export::client::{GraphClient, GRAPH_BASE_URI};
+//!
+//! # fn main() -> microsoft_todo_export::error::Result<()> {
+//! let session = Session::from_access_token("<access token>");
+//! let client = GraphClient::new(reqwest::Client::new(), session, GRAPH_BASE_URI)?;
+//!
+//! for list in client.lists() {
+//!     let list = list?;
+//!
+//!     for task in client.tasks(&list.id) {
+//!         println!("{}: {}", list.display_name, task?.title);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `GraphClient` blocks while each request is sent, on a runtime of its own. Within a `tokio` runtime,
+//! use an `AsyncGraphClient`, which returns a [`reader::CollectionStream`] of each collection instead:
+//!
+//! ```no_run
+//! use futures_util::StreamExt;
+//! use microsoft_todo_export::auth::Session;
+//! use microsoft_todo_export::client::{AsyncGraphClient, GRAPH_BASE_URI};
+//!
+//! # async fn example() -> microsoft_todo_export::error::Result<()> {
+//! let client = AsyncGraphClient::new(reqwest::Client::new(), Session::from_access_token("<access token>"), GRAPH_BASE_URI);
+//!
+//! let me = client.me().await?;
+//! let mut lists = client.lists();
+//!
+//! while let Some(list) = lists.next().await {
+//!     println!("{} has a list named {}", me.display_name, list?.display_name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[macro_use]
+extern crate serde;
+#[macro_use]
+extern crate derive_more;
+
+pub mod error;
+pub mod api;
+pub mod attachments;
+pub mod auth;
+pub mod backup;
+pub mod batch;
+pub mod client;
+pub mod delta;
+pub mod export;
+pub mod pool;
+pub mod reader;
+pub mod restore;
+pub mod retry;
+pub mod timezone;
+
+#[cfg(test)]
+mod stub;
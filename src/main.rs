@@ -1,204 +1,127 @@
-#[macro_use]
-extern crate serde;
-#[macro_use]
-extern crate derive_more;
-
+use std::fs;
 use std::io;
-use serde::de::DeserializeOwned;
-
-mod error;
-mod api;
-
-use api::Collection;
-use error::Result;
+use std::process;
+use clap::Parser;
 
-const GRAPH_BASE_URI: &str = "https://graph.microsoft.com/beta";
+use microsoft_todo_export::auth;
+use microsoft_todo_export::client::GraphClient;
+use microsoft_todo_export::error::{Error, Result};
 
-/// A hacky fucking struct for reading from a paged `Collection`. 
-struct CollectionReader<'a, T> where T: DeserializeOwned + Clone {
-    /// The `reqwest` client from which to read the next links (pages) in the collection.
-    client: &'a reqwest::blocking::Client,
+mod cli;
+mod commands;
 
-    /// The user's OAuth access `token`. 
-    token: &'a str,
+use cli::{AuthArgs, Cli, Command};
 
-    /// The `Collection` to read.
-    collection: Option<api::Collection<T>>,
-
-    /// The full list of `items` which have been read from the `Collection` so far.
-    items: Vec<T>,
-
-    /// When iterating, the current index in the `items` vec.
-    iter_index: usize,
-}
-
-impl<'a, T: DeserializeOwned + Clone> CollectionReader<'a, T> {
-
-    /// Create a new collection reader, given the `client` and the access `token`. 
-    pub fn new(client: &'a reqwest::blocking::Client, token: &'a str) -> Self {
-        Self {
-            client,
-            token,
-            collection: None,
-            items: Vec::new(),
-            iter_index: 0,
-        }
+/// Sign in, either using the token given in the arguments, a cached token, by signing in with a device code, 
+/// or by asking the user to paste a token. 
+fn sign_in(client: &reqwest::blocking::Client, args: &AuthArgs) -> Result<auth::Session> {
+    if let Some(token) = &args.token {
+        return Ok(auth::Session::from_access_token(token.trim()));
     }
 
-    /// First action:
-    /// Fetch the requested collection from the given `url`. 
-    pub fn fetch<S: AsRef<str>>(&mut self, url: S) -> Result<usize> {
-        self.fetch_inner(url)
+    if let Some(path) = &args.token_file {
+        return Ok(auth::Session::from_access_token(fs::read_to_string(path)?.trim()));
     }
 
-    /// Fetch the requested collection from the given `url`. 
-    /// Append the received collection items into the `items` property. 
-    fn fetch_inner<S: AsRef<str>>(&mut self, url: S) -> Result<usize> {
-        self.collection = Some(self.client
-            .get(url.as_ref())
-            .bearer_auth(self.token)
-            .send()?
-            .json()?);
+    let config = match args.oauth_config() {
+        Some(config) => config,
+        None if commands::is_interactive() => return Ok(auth::Session::from_access_token(prompt_for_token())),
+        None => return Err(Error::UsageError("No way to sign in was given; use `--client-id`, `--token` or `--token-file`".to_string())),
+    };
 
-        let new_item_count = self.collection.as_ref().unwrap().value.len();
+    let cache = auth::TokenCache::for_account(&args.account)?;
 
-        // Copy all of the newly fetched items and put them into the `items` vec.
-        self.items.append(&mut self.collection.as_ref().unwrap().value.clone());
+    // Why signing in again is needed, if there's a cached token. 
+    let mut reason = None;
 
-        Ok(new_item_count)
-    }
+    if let Some(token) = cache.load()? {
+        let missing = token.missing_scopes(&config.scopes).join(" ");
 
-    /// Fetch the next page of items into the `items` property.
-    pub fn fetch_next(&mut self) -> Result<usize> {
-        if self.collection.is_none() {
-            return Ok(0);
-        }
+        if missing.is_empty() {
+            let session = auth::Session::new(token, renewal_client()?, config.clone(), Some(cache.clone()));
 
-        let link = self.collection.as_ref().unwrap().odata.next_link.clone();
+            // If the cached token has expired and can't be renewed, sign in again. 
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
 
-        match link {
-            Some(link) => self.fetch_inner(&link),
-            None => Ok(0)
+            match runtime.block_on(session.access_token()) {
+                Ok(_) => return Ok(session),
+                Err(e) => reason = Some(format!("The cached token couldn't be renewed ({})", e)),
+            }
+        } else {
+            reason = Some(format!("The cached token wasn't granted {}", missing));
         }
     }
 
-    /// Does the collection have any further links (pages)? 
-    pub fn has_next_link(&self) -> bool {
-        match &self.collection {
-            Some(c) => c.odata.next_link.is_some(),
-            None => false
-        }
+    // Nobody is there to enter the code, so fail rather than waiting for the code to expire. 
+    if !commands::is_interactive() {
+        let reason = reason.unwrap_or_else(|| "No token is cached".to_string());
+        return Err(Error::UsageError(format!("{}, and signing in needs a terminal; run the command in one to sign in", reason)));
     }
-}
-
-impl<'a, T: DeserializeOwned + Clone> Iterator for CollectionReader<'a, T> {
-    type Item = T;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.collection.is_none() {
-            return None;
-        }
-
-        if self.collection.as_ref().unwrap().value.is_empty() {
-            return None;
-        }
-
-        // If we're at the last item in the currently loaded items
-        if self.iter_index == self.items.len() {
-            if !self.has_next_link() {
-                return None;
-            }
+    if let Some(reason) = reason {
+        eprintln!("{}; signing in again", reason);
+    }
 
-            self.fetch_next().expect("Failed to fetch next items!");
+    let response = auth::DeviceCodeFlow::new(client, &config).sign_in(|code| {
+        match &code.message {
+            Some(message) => eprintln!("{}", message),
+            None => eprintln!("To sign in, open {} and enter the code {}", code.verification_uri, code.user_code),
         }
+    })?;
 
-        if self.iter_index == self.items.len() {
-            return None;
-        }
+    eprintln!();
 
-        let fetch_index = self.iter_index;
-        self.iter_index += 1;
+    let mut token = auth::CachedToken::from_response(response, None);
 
-        self.items.get(fetch_index).map(|item_ref| item_ref.clone())
+    // The token endpoint should say which scopes were granted, but if it doesn't, assume they're those requested. 
+    if token.scopes.is_empty() {
+        token.scopes = config.scopes.clone();
     }
-}
+    cache.store(&token)?;
 
+    Ok(auth::Session::new(token, renewal_client()?, config, Some(cache)))
+}
 
-fn graph_url(path: &str) -> String {
-    format!("{}{}", GRAPH_BASE_URI, path)
+/// The client used to renew access tokens. 
+/// Renewals are an hour or so apart, and may be made from different runtimes, so connections aren't kept open between them. 
+fn renewal_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder().pool_max_idle_per_host(0).build()?)
 }
 
-fn main() -> Result<()> {
+/// Ask the user to paste an access token. 
+fn prompt_for_token() -> String {
     // To acquire OAuth token, grant all "Tasks" permissions within MS Graph Explorer, then click "Access Token"
     // See: https://blog.osull.com/2020/09/14/backup-migrate-microsoft-to-do-tasks-with-powershell-and-microsoft-graph/
     // See: https://gotoguy.blog/2020/05/06/oauth-authentication-to-microsoft-graph-with-powershell-core/
-    println!("Paste OAuth2 Token");
+    eprintln!("Paste OAuth2 Token");
 
     let mut token = String::new();
     io::stdin().read_line(&mut token).expect("Failed to read line");
-    let token = token.trim();
 
-    println!();
+    eprintln!();
 
-    let client = reqwest::blocking::Client::new();
-    
-    let response = client.get(graph_url("/me"))
-        .bearer_auth(token)
-        .send()?;
-
-    let me = match response.json::<api::Response<api::user::User>>()? {
-        api::Response::Success(me) => me,
-        api::Response::Error(e) => {
-            println!("ERROR: Code: {} Message: {}", e.error.code, e.error.message);
-            return Ok(());
-        }
-    };
-
-    println!("User: {} / {}", me.display_name, me.user_principal_name);
-
-    let lists: Collection<api::tasks::TodoTaskList> = client.get(graph_url("/me/todo/lists"))
-        .bearer_auth(token)
-        .send()?
-        .json()?;
+    token.trim().to_string()
+}
 
-    println!();
-    println!("Todo Lists:");
+fn main() {
+    let cli = Cli::parse();
 
-    for (i, list) in lists.value.iter().enumerate() {
-        println!("{}. {}", i + 1, list.display_name);
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
     }
+}
 
-    println!();
-    println!("Enter number of list to fetch: ");
-
-    let selected_list: &api::tasks::TodoTaskList = {
-        loop {
-            let mut index_str = String::new();
-            io::stdin().read_line(&mut index_str).expect("Failed to read selected list!");
-            let selected_list_index: u32 = index_str.trim().parse()?;
-
-            let selected_list = lists.value.get((selected_list_index as usize) - 1);
-
-            if let Some(list) = selected_list {
-                break list;
-            }
-        }
-    };
-
-    println!();
-    println!("Fetching list: {} ({})", selected_list.display_name, selected_list.id);
-
-    let fetch_url = graph_url(&format!("/me/todo/lists/{}/tasks", selected_list.id));
-
-    let mut task_collection = CollectionReader::<api::tasks::TodoTask>::new(&client, &token);
-    task_collection.fetch(fetch_url)?;
-
-    println!();
-    println!("Tasks: ");
-
-    for task in task_collection {
-        println!("{}", task.title);
+fn run(cli: Cli) -> Result<()> {
+    let http = reqwest::blocking::Client::new();
+    let session = sign_in(&http, &cli.auth)?;
+    let client = GraphClient::new(reqwest::Client::new(), session, &cli.graph_url)?.with_retry_policy(cli.retry_policy());
+
+    match cli.command {
+        Some(Command::Whoami) => commands::whoami(&client),
+        Some(Command::Lists) => commands::lists(&client),
+        Some(Command::Export(args)) => commands::export(&client, &args),
+        Some(Command::Restore(args)) => commands::restore(&client, &args),
+        None => commands::export(&client, &Default::default()),
     }
-
-    Ok(())
 }
@@ -0,0 +1,122 @@
+//! Doing the same work for each of several items at once, on a fixed number of threads, such as fetching each list.
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use crate::error::Result;
+
+/// Call `work` with each of the `items`, on up to `concurrency` threads at once, returning the results in the order
+/// of the `items`, whichever finishes first.
+///
+/// Each thread takes the next item as it finishes one. Once an item fails, no more are started, and the error of
+/// the first item to fail, in the order of the `items`, is returned. A panic in `work` is resumed on this thread.
+pub fn try_map<T, R, F>(items: &[T], concurrency: usize, work: F) -> Result<Vec<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Result<R> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+
+    let worker = || {
+        let mut results = Vec::new();
+
+        while !failed.load(Ordering::Relaxed) {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+
+            let item = match items.get(i) {
+                Some(item) => item,
+                None => break,
+            };
+
+            let result = work(item);
+            failed.fetch_or(result.is_err(), Ordering::Relaxed);
+
+            results.push((i, result));
+        }
+
+        results
+    };
+
+    let mut results: Vec<(usize, Result<R>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..concurrency.clamp(1, items.len().max(1)))
+            .map(|_| scope.spawn(worker))
+            .collect();
+
+        workers.into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::error::Error;
+
+    /// Sleep for the given number of milliseconds.
+    fn sleep(millis: u64) {
+        thread::sleep(Duration::from_millis(millis));
+    }
+
+    #[test]
+    fn results_are_in_the_order_of_the_items() {
+        let finished = Mutex::new(Vec::new());
+
+        // Later items finish first.
+        let results = try_map(&[0, 1, 2, 3], 4, |&i| {
+            sleep(200 - i * 50);
+            finished.lock().unwrap().push(i);
+            Ok(i * 10)
+        }).unwrap();
+
+        assert_eq!(results, vec![0, 10, 20, 30]);
+        assert_eq!(finished.into_inner().unwrap(), vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn the_first_item_to_fail_is_reported() {
+        // The last item fails first, but the second item is before it.
+        let result = try_map(&[0, 1, 2, 3], 4, |&i| match i {
+            1 => {
+                sleep(200);
+                Err(Error::UsageError("1 failed".to_string()))
+            },
+            3 => Err(Error::UsageError("3 failed".to_string())),
+            _ => Ok(i),
+        });
+
+        match result {
+            Err(Error::UsageError(message)) => assert_eq!(message, "1 failed"),
+            other => panic!("expected the second item's error, got: {:?}", other.map_err(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn no_more_items_are_started_after_a_failure() {
+        let started = AtomicUsize::new(0);
+
+        let result = try_map(&[0, 1, 2, 3], 1, |&i| {
+            started.fetch_add(1, Ordering::Relaxed);
+
+            if i == 1 { Err(Error::UsageError("failed".to_string())) } else { Ok(i) }
+        });
+
+        assert!(result.is_err());
+        assert_eq!(started.into_inner(), 2);
+    }
+
+    #[test]
+    fn no_items() {
+        assert_eq!(try_map(&[] as &[u32], 4, |&i| Ok(i)).unwrap(), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,224 @@
+//! Reading every item of a paged collection, either as an `Iterator` or as a `Stream`.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::vec;
+
+use futures_util::ready;
+use futures_util::stream::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::api::Collection;
+use crate::client::{AsyncGraphClient, GraphClient};
+use crate::error::Result;
+
+/// A request for a page of a collection, which is in progress.
+type PageFuture<'a, T> = Pin<Box<dyn Future<Output = Result<Collection<T>>> + Send + 'a>>;
+
+/// Reads every item of a paged `Collection`, fetching each page as it's needed.
+///
+/// Only the current page is held in memory; items are moved out as they're yielded,
+/// so reading a collection of any size uses a constant amount of memory.
+/// If fetching a page fails, the error is yielded and the stream ends.
+///
+/// This is the `Stream` counterpart of a `CollectionReader`, for use with an `AsyncGraphClient`.
+pub struct CollectionStream<'a, T> {
+    /// The client from which to read the next links (pages) in the collection.
+    client: &'a AsyncGraphClient,
+
+    /// The items of the current page which haven't been yielded yet.
+    page: vec::IntoIter<T>,
+
+    /// The URL of the next page to fetch; `None` once the last page has been fetched.
+    next_link: Option<String>,
+
+    /// The delta link given on the last page of a delta query.
+    delta_link: Option<String>,
+
+    /// The request for the next page, while it's being fetched.
+    fetching: Option<PageFuture<'a, T>>,
+}
+
+impl<'a, T: DeserializeOwned> CollectionStream<'a, T> {
+    /// Create a new collection stream, which reads the collection at the given `url` using the `client`.
+    /// Nothing is fetched until the first item is read.
+    pub fn new<S: Into<String>>(client: &'a AsyncGraphClient, url: S) -> Self {
+        Self {
+            client,
+            page: Vec::new().into_iter(),
+            next_link: Some(url.into()),
+            delta_link: None,
+            fetching: None,
+        }
+    }
+
+    /// The `@odata.deltaLink` of a delta query, from which the changes made after it can be fetched.
+    /// This is only known once every item has been read.
+    pub fn delta_link(&self) -> Option<&str> {
+        self.delta_link.as_deref()
+    }
+}
+
+// Nothing is pinned in place; the request for a page is boxed.
+impl<T> Unpin for CollectionStream<'_, T> {}
+
+impl<'a, T: DeserializeOwned + Send + 'a> Stream for CollectionStream<'a, T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(fetching) = &mut this.fetching {
+                let result = ready!(fetching.as_mut().poll(cx));
+                this.fetching = None;
+
+                match result {
+                    Ok(collection) => {
+                        this.page = collection.value.into_iter();
+                        this.next_link = collection.odata.next_link;
+                        this.delta_link = collection.odata.delta_link;
+                    },
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            }
+
+            if let Some(item) = this.page.next() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            // Pages may be empty, even when there are further pages, so keep going until an item is found.
+            let link = match this.next_link.take() {
+                Some(link) => link,
+                None => return Poll::Ready(None),
+            };
+
+            let client = this.client;
+            this.fetching = Some(Box::pin(async move { client.get_json(&link).await }));
+        }
+    }
+}
+
+/// Reads every item of a paged `Collection`, blocking while each page is fetched.
+///
+/// This is a thin wrapper around a `CollectionStream`, for use with a (blocking) `GraphClient`.
+pub struct CollectionReader<'a, T> {
+    /// The client whose runtime the stream is read on.
+    client: &'a GraphClient,
+
+    /// The stream of the collection's items.
+    stream: CollectionStream<'a, T>,
+}
+
+impl<'a, T: DeserializeOwned> CollectionReader<'a, T> {
+    /// Create a new collection reader, which reads the collection at the given `url` using the `client`.
+    /// Nothing is fetched until the first item is read.
+    pub fn new<S: Into<String>>(client: &'a GraphClient, url: S) -> Self {
+        Self::from_stream(client, CollectionStream::new(client.async_client(), url))
+    }
+
+    /// Read the `stream`, of the `client`'s `AsyncGraphClient`, on the `client`'s runtime.
+    pub fn from_stream(client: &'a GraphClient, stream: CollectionStream<'a, T>) -> Self {
+        Self { client, stream }
+    }
+
+    /// The `@odata.deltaLink` of a delta query, from which the changes made after it can be fetched.
+    /// This is only known once every item has been read.
+    pub fn delta_link(&self) -> Option<&str> {
+        self.stream.delta_link()
+    }
+}
+
+impl<'a, T: DeserializeOwned + Send + 'a> Iterator for CollectionReader<'a, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.client.block_on(self.stream.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream::TryStreamExt;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::stub::{self, Response};
+
+    /// A page of a collection, with the given item IDs, and `@odata` links.
+    fn page(ids: &[&str], mut links: serde_json::Value) -> Response {
+        links["value"] = ids.iter().map(|id| json!({ "id": id })).collect();
+
+        Response::json(200, links.to_string())
+    }
+
+    /// Three pages, linked to each other on the stub at `url`, the second of which is empty, and the last of which
+    /// has a delta link.
+    fn pages(url: &str) -> Vec<Response> {
+        vec![
+            page(&["a", "b"], json!({ "@odata.nextLink": format!("{}/items?$skiptoken=1", url) })),
+            page(&[], json!({ "@odata.nextLink": format!("{}/items?$skiptoken=2", url) })),
+            page(&["c"], json!({ "@odata.deltaLink": format!("{}/items?$deltatoken=3", url) })),
+        ]
+    }
+
+    fn ids(items: Vec<serde_json::Value>) -> Vec<String> {
+        items.iter().map(|item| item["id"].as_str().unwrap().to_string()).collect()
+    }
+
+    fn paths(requests: Vec<stub::Request>) -> Vec<String> {
+        requests.into_iter().map(|request| request.path).collect()
+    }
+
+    #[test]
+    fn reader_follows_next_links() {
+        let (url, requests) = stub::serve_with(pages);
+        let client = stub::client(&url);
+
+        let mut reader = CollectionReader::new(&client, client.url("/items"));
+        assert_eq!(reader.delta_link(), None);
+
+        let items: Vec<serde_json::Value> = reader.by_ref().collect::<Result<_>>().unwrap();
+
+        assert_eq!(ids(items), vec!["a", "b", "c"]);
+        assert_eq!(reader.delta_link(), Some(format!("{}/items?$deltatoken=3", url).as_str()));
+        assert_eq!(paths(requests.join().unwrap()), vec!["/items", "/items?$skiptoken=1", "/items?$skiptoken=2"]);
+    }
+
+    #[test]
+    fn stream_follows_next_links() {
+        let (url, requests) = stub::serve_with(pages);
+        let client = stub::client(&url);
+
+        let mut stream = CollectionStream::new(client.async_client(), client.url("/items"));
+        let items: Vec<serde_json::Value> = client.block_on(stream.by_ref().try_collect()).unwrap();
+
+        assert_eq!(ids(items), vec!["a", "b", "c"]);
+        assert_eq!(stream.delta_link(), Some(format!("{}/items?$deltatoken=3", url).as_str()));
+        assert_eq!(paths(requests.join().unwrap()), vec!["/items", "/items?$skiptoken=1", "/items?$skiptoken=2"]);
+    }
+
+    #[test]
+    fn an_error_ends_the_reader() {
+        let (url, requests) = stub::serve_with(|url| vec![
+            page(&["a"], json!({ "@odata.nextLink": format!("{}/items?$skiptoken=1", url) })),
+            Response::json(404, json!({ "error": { "code": "ErrorItemNotFound", "message": "Not found" } }).to_string()),
+        ]);
+        let client = stub::client(&url);
+
+        let mut reader = CollectionReader::<serde_json::Value>::new(&client, client.url("/items"));
+
+        assert_eq!(reader.next().unwrap().unwrap()["id"], "a");
+        match reader.next() {
+            Some(Err(Error::GraphError(e))) => assert_eq!(e.status, StatusCode::NOT_FOUND),
+            other => panic!("expected the page to fail, got: {:?}", other.map(|item| item.ok())),
+        }
+        assert!(reader.next().is_none());
+        assert_eq!(reader.delta_link(), None);
+
+        assert_eq!(requests.join().unwrap().len(), 2);
+    }
+}
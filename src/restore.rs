@@ -0,0 +1,438 @@
+//! Recreating the lists and tasks of a backup in the signed in account.
+
+use std::collections::BTreeMap;
+
+use reqwest::StatusCode;
+
+use crate::api::{DateTimeOffset, DateTimeTimeZone, Extension};
+use crate::api::tasks::{ChecklistItem, Importance, ItemBody, LinkedResource, PatternedRecurrence, TaskStatus, TodoTask, TodoTaskList, WellknownListName};
+use crate::backup::{Backup, ListBackup};
+use crate::client::{self, GraphClient};
+use crate::error::{Error, Result};
+
+/// The body of a request to create a `TodoTaskList`.
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/todo-post-lists?view=graph-rest-1.0>
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewTodoTaskList<'a> {
+    display_name: &'a str,
+}
+
+/// The body of a request to create a `TodoTask`.
+/// Only the writable properties of the task are included; the rest are assigned by Graph.
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/todotasklist-post-tasks?view=graph-rest-1.0>
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewTodoTask<'a> {
+    title: &'a str,
+
+    body: &'a ItemBody,
+
+    importance: &'a Importance,
+
+    status: &'a TaskStatus,
+
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    categories: &'a [String],
+
+    is_reminder_on: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reminder_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed_date_time: Option<&'a DateTimeTimeZone>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recurrence: Option<&'a PatternedRecurrence>,
+}
+
+impl<'a> From<&'a TodoTask> for NewTodoTask<'a> {
+    fn from(task: &'a TodoTask) -> Self {
+        Self {
+            title: &task.title,
+            body: &task.body,
+            importance: &task.importance,
+            status: &task.status,
+            categories: &task.categories,
+            is_reminder_on: task.is_reminder_on,
+            reminder_date_time: task.reminder_date_time.as_ref(),
+            due_date_time: task.due_date_time.as_ref(),
+            start_date_time: task.start_date_time.as_ref(),
+            completed_date_time: task.completed_date_time.as_ref(),
+            // Graph rejects a recurring task which has no due date.
+            recurrence: task.recurrence.as_ref().filter(|_| task.due_date_time.is_some()),
+        }
+    }
+}
+
+/// The body of a request to create a `ChecklistItem`.
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/todotask-post-checklistitems?view=graph-rest-1.0>
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewChecklistItem<'a> {
+    display_name: &'a str,
+
+    is_checked: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checked_date_time: Option<&'a DateTimeOffset>,
+}
+
+/// The body of a request to create a `LinkedResource`.
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/todotask-post-linkedresources?view=graph-rest-1.0>
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewLinkedResource<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    web_url: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    application_name: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_id: Option<&'a str>,
+}
+
+impl<'a> From<&'a LinkedResource> for NewLinkedResource<'a> {
+    fn from(resource: &'a LinkedResource) -> Self {
+        Self {
+            web_url: resource.web_url.as_deref(),
+            application_name: resource.application_name.as_deref(),
+            display_name: resource.display_name.as_deref(),
+            external_id: resource.external_id.as_deref(),
+        }
+    }
+}
+
+/// The body of a request to create an open `Extension`. 
+/// The extension's data is sent exactly as it was backed up, except for any OData annotations.
+///
+/// See: <https://docs.microsoft.com/en-us/graph/api/opentypeextension-post-opentypeextension?view=graph-rest-1.0>
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct NewExtension<'a> {
+    #[serde(rename = "@odata.type")]
+    odata_type: &'a str,
+
+    extension_name: &'a str,
+
+    #[serde(flatten)]
+    data: BTreeMap<&'a str, &'a serde_json::Value>,
+}
+
+impl<'a> From<&'a Extension> for NewExtension<'a> {
+    fn from(extension: &'a Extension) -> Self {
+        Self {
+            odata_type: "microsoft.graph.openTypeExtension",
+            extension_name: extension.extension_name.as_deref().unwrap_or(&extension.id),
+            data: extension.data.iter()
+                .filter(|(key, _)| !key.starts_with('@'))
+                .map(|(key, value)| (key.as_str(), value))
+                .collect(),
+        }
+    }
+}
+
+/// The part of a created resource's response which the restore needs. 
+#[derive(Deserialize, Debug)]
+struct Created {
+    id: String,
+}
+
+/// The IDs of the lists, tasks, steps and links which were created by a restore, keyed by their IDs in the backup.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdMap {
+    pub lists: BTreeMap<String, String>,
+
+    pub tasks: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub checklist_items: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub linked_resources: BTreeMap<String, String>,
+}
+
+/// Called as each list of the backup starts being restored, with the existing list its tasks are restored into, 
+/// or `None` if a new list is created for them.
+type Progress<'a> = Box<dyn FnMut(&ListBackup, Option<&TodoTaskList>) + 'a>;
+
+/// Recreates the lists and tasks of a `Backup` in the signed in user's account.
+///
+/// New lists are created for each list in the backup, except for the default list,
+/// whose tasks are restored into the account's existing default list.
+pub struct Restorer<'a> {
+    client: &'a GraphClient,
+
+    /// The lists which already exist in the account.
+    existing_lists: Vec<TodoTaskList>,
+
+    /// The IDs of everything restored so far.
+    ids: IdMap,
+
+    progress: Option<Progress<'a>>,
+}
+
+impl<'a> Restorer<'a> {
+    /// Create a restorer which restores into the account of the `client`, which currently has the `existing_lists`.
+    pub fn new(client: &'a GraphClient, existing_lists: Vec<TodoTaskList>) -> Self {
+        Self {
+            client,
+            existing_lists,
+            ids: IdMap::default(),
+            progress: None,
+        }
+    }
+
+    /// Call `progress` as each list starts being restored, with the existing list its tasks are restored into, 
+    /// or `None` if a new list is created for them.
+    pub fn with_progress<F: FnMut(&ListBackup, Option<&TodoTaskList>) + 'a>(mut self, progress: F) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// The IDs of everything restored so far.
+    /// If a restore fails part way through, this records what was created before the failure.
+    pub fn ids(&self) -> &IdMap {
+        &self.ids
+    }
+
+    /// Restore every list, and the tasks within them, from the `backup`.
+    pub fn restore(&mut self, backup: &Backup) -> Result<()> {
+        for list in &backup.lists {
+            self.restore_list(list)?;
+        }
+
+        Ok(())
+    }
+
+    /// Restore a single list, and its tasks.
+    fn restore_list(&mut self, backup: &ListBackup) -> Result<()> {
+        let existing = self.existing_default_list(&backup.list).cloned();
+
+        if let Some(progress) = &mut self.progress {
+            progress(backup, existing.as_ref());
+        }
+
+        let list_id = match existing {
+            Some(existing) => existing.id,
+            None => self.create_list(&backup.list)?.id,
+        };
+
+        self.ids.lists.insert(backup.list.id.clone(), list_id.clone());
+
+        let list_path = format!("/me/todo/lists/{}", client::encode_segment(&list_id));
+        self.restore_extensions(&list_path, &backup.list.extensions)?;
+
+        for task in &backup.tasks {
+            let created = self.create_task(&list_id, task)?;
+            self.ids.tasks.insert(task.id.clone(), created.id.clone());
+
+            for item in &task.checklist_items {
+                let created_item = self.create_checklist_item(&list_id, &created.id, item)?;
+                self.ids.checklist_items.insert(item.id.clone(), created_item.id);
+            }
+
+            for resource in &task.linked_resources {
+                let created_resource = self.create_linked_resource(&list_id, &created.id, resource)?;
+                self.ids.linked_resources.insert(resource.id.clone(), created_resource.id);
+            }
+
+            self.restore_extensions(&format!("{}/tasks/{}", list_path, client::encode_segment(&created.id)), &task.extensions)?;
+        }
+
+        Ok(())
+    }
+
+    /// If the `list` is the default list, find the account's existing default list.
+    /// The default list can't be created, or deleted, so there's always exactly one.
+    fn existing_default_list(&self, list: &TodoTaskList) -> Option<&TodoTaskList> {
+        match list.wellknown_list_name {
+            WellknownListName::DefaultList => self.existing_lists.iter()
+                .find(|existing| matches!(existing.wellknown_list_name, WellknownListName::DefaultList)),
+            _ => None,
+        }
+    }
+
+    fn create_list(&self, list: &TodoTaskList) -> Result<Created> {
+        self.client.post_json(&self.client.url("/me/todo/lists"), &NewTodoTaskList {
+            display_name: &list.display_name,
+        })
+    }
+
+    fn create_task(&self, list_id: &str, task: &TodoTask) -> Result<Created> {
+        self.client.post_json(
+            &self.client.url(&format!("/me/todo/lists/{}/tasks", client::encode_segment(list_id))),
+            &NewTodoTask::from(task),
+        )
+    }
+
+    fn create_checklist_item(&self, list_id: &str, task_id: &str, item: &ChecklistItem) -> Result<Created> {
+        self.client.post_json(
+            &self.client.url(&format!("/me/todo/lists/{}/tasks/{}/checklistItems", client::encode_segment(list_id), client::encode_segment(task_id))),
+            &NewChecklistItem {
+                display_name: &item.display_name,
+                is_checked: item.is_checked,
+                checked_date_time: item.checked_date_time.as_ref(),
+            },
+        )
+    }
+
+    /// Recreate the open `extensions` of the resource at `path`, eg: `/me/todo/lists/{id}`, whose IDs are already encoded. 
+    ///
+    /// The existing default list may already have an extension of the same name, in which case it's updated instead.
+    fn restore_extensions(&self, path: &str, extensions: &[Extension]) -> Result<()> {
+        for extension in extensions {
+            let body = NewExtension::from(extension);
+
+            match self.client.post(&self.client.url(&format!("{}/extensions", path)), &body) {
+                Ok(_) => {},
+                Err(Error::GraphError(e)) if e.status == StatusCode::CONFLICT => {
+                    self.client.patch(&self.client.url(&format!("{}/extensions/{}", path, client::encode_segment(body.extension_name))), &body)?;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_linked_resource(&self, list_id: &str, task_id: &str, resource: &LinkedResource) -> Result<Created> {
+        self.client.post_json(
+            &self.client.url(&format!("/me/todo/lists/{}/tasks/{}/linkedResources", client::encode_segment(list_id), client::encode_segment(task_id))),
+            &NewLinkedResource::from(resource),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::api::tasks::fixtures::{self, task};
+    use crate::stub::{self, Response};
+
+    const RECURRENCE: &str = r#"{
+        "pattern": { "type": "daily", "interval": 1 },
+        "range": { "type": "noEnd", "startDate": "2021-05-03", "recurrenceTimeZone": null }
+    }"#;
+
+    fn extension() -> Extension {
+        serde_json::from_value(json!({
+            "@odata.type": "#microsoft.graph.openTypeExtension",
+            "@odata.context": "https://graph.microsoft.com/beta/$metadata#extensions/$entity",
+            "id": "com.example.settings",
+            "extensionName": "com.example.settings",
+            "colour": "red",
+            "@odata.etag": "W/\"1\"",
+        })).unwrap()
+    }
+
+    #[test]
+    fn only_writable_task_properties_are_sent() {
+        let task = task(json!({
+            "title": "Water the plants",
+            "categories": ["Home"],
+            "hasAttachments": true,
+            "bodyLastModifiedDateTime": "2021-03-01T10:00:00Z",
+            "dueDateTime": { "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "UTC" },
+            "recurrence": serde_json::from_str::<serde_json::Value>(RECURRENCE).unwrap(),
+            "@odata.etag": "W/\"1\"",
+            "someNewProperty": 1,
+        }));
+
+        assert_eq!(serde_json::to_value(NewTodoTask::from(&task)).unwrap(), json!({
+            "title": "Water the plants",
+            "body": { "content": "", "contentType": "text" },
+            "importance": "normal",
+            "status": "notStarted",
+            "categories": ["Home"],
+            "isReminderOn": false,
+            "dueDateTime": { "dateTime": "2021-05-03T09:00:00.0000000", "timeZone": "UTC" },
+            "recurrence": serde_json::from_str::<serde_json::Value>(RECURRENCE).unwrap(),
+        }));
+    }
+
+    #[test]
+    fn recurrences_without_a_due_date_are_dropped() {
+        let task = task(json!({ "recurrence": serde_json::from_str::<serde_json::Value>(RECURRENCE).unwrap() }));
+        let body = serde_json::to_value(NewTodoTask::from(&task)).unwrap();
+
+        assert_eq!(body.get("recurrence"), None);
+        assert_eq!(body.get("dueDateTime"), None);
+    }
+
+    #[test]
+    fn extension_annotations_are_stripped() {
+        let extension = extension();
+
+        assert_eq!(serde_json::to_value(NewExtension::from(&extension)).unwrap(), json!({
+            "@odata.type": "microsoft.graph.openTypeExtension",
+            "extensionName": "com.example.settings",
+            "colour": "red",
+        }));
+    }
+
+    #[test]
+    fn existing_extensions_are_updated() {
+        let mut default_list = fixtures::list("Tasks");
+        default_list.wellknown_list_name = WellknownListName::DefaultList;
+
+        let mut existing = default_list.clone();
+        existing.id = "existing/list".to_string();
+
+        let mut backup = Backup::new(None);
+        backup.lists.push(ListBackup {
+            list: TodoTaskList { extensions: vec![extension()], ..default_list },
+            tasks: vec![task(json!({
+                "checklistItems": [{ "id": "item", "displayName": "Semi-skimmed", "isChecked": false, "createdDateTime": "2021-03-01T10:00:00Z" }],
+            }))],
+            delta_link: None,
+        });
+
+        let (url, requests) = stub::serve(vec![
+            Response::json(409, r#"{"error":{"code":"NameAlreadyExists","message":"An extension with this name already exists."}}"#),
+            Response::json(200, "{}"),
+            Response::json(201, r#"{"id":"new-task"}"#),
+            Response::json(201, r#"{"id":"new-item"}"#),
+        ]);
+        let client = stub::client(&url);
+
+        let mut restored_into = Vec::new();
+        let mut restorer = Restorer::new(&client, vec![existing])
+            .with_progress(|_, existing| restored_into.push(existing.map(|list| list.display_name.clone())));
+
+        restorer.restore(&backup).unwrap();
+
+        assert_eq!(restorer.ids().lists["AAMkAGI2"], "existing/list");
+        assert_eq!(restorer.ids().tasks["AAMkAGI1"], "new-task");
+        assert_eq!(restorer.ids().checklist_items["item"], "new-item");
+        drop(restorer);
+
+        assert_eq!(restored_into, vec![Some("Tasks".to_string())]);
+
+        let requests: Vec<(String, String)> = requests.join().unwrap().into_iter().map(|request| (request.method, request.path)).collect();
+        assert_eq!(requests, vec![
+            ("POST".to_string(), "/me/todo/lists/existing%2Flist/extensions".to_string()),
+            ("PATCH".to_string(), "/me/todo/lists/existing%2Flist/extensions/com.example.settings".to_string()),
+            ("POST".to_string(), "/me/todo/lists/existing%2Flist/tasks".to_string()),
+            ("POST".to_string(), "/me/todo/lists/existing%2Flist/tasks/new-task/checklistItems".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,183 @@
+//! Retrying requests which were throttled by Graph, or failed with a transient error.
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+/// How requests which failed because of throttling, or a transient error, are retried.
+///
+/// See: <https://docs.microsoft.com/en-us/graph/throttling>
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of times a single request is retried.
+    pub max_retries: u32,
+
+    /// The maximum total time to spend waiting between retries of a single request.
+    pub max_total_wait: Duration,
+
+    /// The delay before the first retry, when Graph doesn't say how long to wait.
+    /// This doubles with each subsequent retry.
+    pub base_delay: Duration,
+
+    /// The longest delay between two retries, when Graph doesn't say how long to wait.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            max_total_wait: Duration::from_secs(5 * 60),
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The exponential backoff before the given retry `attempt` (starting from `0`), with "full jitter";
+    /// a random delay between zero and the exponentially increasing upper bound.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let upper_bound = self.base_delay
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+
+        upper_bound.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// The delay before the given retry `attempt`, preferring the delay requested in a `Retry-After` header.
+    pub fn delay(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+        headers.and_then(retry_after).unwrap_or_else(|| self.backoff(attempt))
+    }
+}
+
+/// Should a request which received a response with the given `status` be retried?
+///
+/// A request which isn't `idempotent`, such as a POST creating a task, may have been carried out despite a `502` or `504`,
+/// so it's only retried when it was throttled, which means it wasn't carried out.
+pub fn is_retryable_status(status: StatusCode, idempotent: bool) -> bool {
+    match status {
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => idempotent,
+        _ => is_throttled_status(status),
+    }
+}
+
+/// Was a request which received a response with the given `status` throttled, rather than failing on its own?
+/// Graph responds with `503 Service Unavailable` to throttle some services, as well as `429 Too Many Requests`.
+pub fn is_throttled_status(status: StatusCode) -> bool {
+    matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// Should a request which failed with the given `error`, without receiving a response, be retried?
+/// A request which timed out may have been carried out, so is only retried if it's `idempotent`,
+/// whereas one which couldn't connect was never sent.
+pub fn is_retryable_error(error: &reqwest::Error, idempotent: bool) -> bool {
+    (error.is_timeout() && idempotent) || error.is_connect()
+}
+
+/// The delay requested by a `Retry-After` header, which may be either a number of seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+
+    // A date in the past means the request may be retried immediately.
+    Some(SystemTime::from(date).duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// A request was still failing when the `RetryPolicy` ran out of retries.
+#[derive(Debug)]
+pub struct RetriesExhausted {
+    /// The number of times the request was sent.
+    pub attempts: u32,
+
+    /// The status of the last response, if one was received.
+    pub last_status: Option<StatusCode>,
+
+    /// The error of the last attempt, if no response was received.
+    pub last_error: Option<reqwest::Error>,
+}
+
+impl fmt::Display for RetriesExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gave up after {} attempts", self.attempts)?;
+
+        match (&self.last_status, &self.last_error) {
+            (Some(status), _) => write!(f, "; the last response was {}", status),
+            (None, Some(error)) => write!(f, "; the last error was: {}", error),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(retry_after(&headers("120")), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&headers(" 0 ")), Some(Duration::from_secs(0)));
+        assert_eq!(retry_after(&headers("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn retry_after_dates() {
+        let future = (Utc::now() + chrono::Duration::seconds(90)).format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let delay = retry_after(&headers(&future)).unwrap();
+
+        // The date is only to the second, and a little time passes before it's read.
+        assert!(delay > Duration::from_secs(85) && delay <= Duration::from_secs(90), "{:?}", delay);
+
+        assert_eq!(retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")), Some(Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn backoff_is_within_the_max_delay() {
+        let policy = RetryPolicy { base_delay: Duration::from_secs(1), max_delay: Duration::from_secs(10), ..RetryPolicy::default() };
+
+        for attempt in 0..100 {
+            let upper_bound = Duration::from_secs(2u64.saturating_pow(attempt).min(10));
+            assert!(policy.backoff(attempt) <= upper_bound, "attempt {}", attempt);
+        }
+
+        // A delay from Graph is used as it is.
+        assert_eq!(policy.delay(50, Some(&headers("120"))), Duration::from_secs(120));
+        assert!(policy.delay(50, Some(&HeaderMap::new())) <= policy.max_delay);
+    }
+
+    #[test]
+    fn only_throttled_requests_are_retried_unless_idempotent() {
+        for status in &[StatusCode::TOO_MANY_REQUESTS, StatusCode::SERVICE_UNAVAILABLE] {
+            assert!(is_retryable_status(*status, true));
+            assert!(is_retryable_status(*status, false));
+        }
+
+        for status in &[StatusCode::BAD_GATEWAY, StatusCode::GATEWAY_TIMEOUT] {
+            assert!(is_retryable_status(*status, true));
+            assert!(!is_retryable_status(*status, false));
+        }
+
+        for status in &[StatusCode::OK, StatusCode::BAD_REQUEST, StatusCode::NOT_FOUND, StatusCode::INTERNAL_SERVER_ERROR] {
+            assert!(!is_retryable_status(*status, true));
+            assert!(!is_retryable_status(*status, false));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! A local stand-in for Graph, and the authorization server, for tests; it serves a scripted response to each request in turn.
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::auth::Session;
+use crate::client::GraphClient;
+use crate::retry::RetryPolicy;
+
+/// How long to wait for the next request before giving up, so that a test whose client stops early doesn't hang.
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A client of the stub at `url`, which retries twice, without waiting unless it's asked to.
+pub fn client(url: &str) -> GraphClient {
+    let policy = RetryPolicy {
+        max_retries: 2,
+        max_total_wait: Duration::from_secs(5),
+        base_delay: Duration::from_secs(0),
+        max_delay: Duration::from_secs(0),
+    };
+
+    GraphClient::new(reqwest::Client::new(), Session::from_access_token("token"), url).unwrap().with_retry_policy(policy)
+}
+
+/// A scripted response.
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json<S: Into<String>>(status: u16, body: S) -> Self {
+        Self { status, headers: Vec::new(), body: body.into() }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A request which was received, in the order it was received.
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+
+    /// The path and query, eg: `/me/todo/lists?$top=1`.
+    pub path: String,
+
+    pub body: String,
+}
+
+impl Request {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("the request body should be JSON")
+    }
+}
+
+/// Serve each of the `responses` in turn, to one request each. Returns the server's URL, and a handle which yields
+/// the requests it received, once every response has been served, or no request has arrived for a while.
+pub fn serve(responses: Vec<Response>) -> (String, thread::JoinHandle<Vec<Request>>) {
+    serve_with(|_| responses)
+}
+
+/// Serve the responses built from the server's URL, eg: for responses which link to further requests.
+pub fn serve_with<F: FnOnce(&str) -> Vec<Response>>(responses: F) -> (String, thread::JoinHandle<Vec<Request>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let responses = responses(&url);
+
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+
+        for response in responses {
+            let stream = match accept(&listener) {
+                Some(stream) => stream,
+                None => break,
+            };
+
+            requests.push(respond(stream, &response));
+        }
+
+        requests
+    });
+
+    (url, handle)
+}
+
+fn accept(listener: &TcpListener) -> Option<TcpStream> {
+    let deadline = Instant::now() + ACCEPT_TIMEOUT;
+
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false).unwrap();
+                return Some(stream);
+            },
+            Err(e) if e.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Read a single request from the `stream`, and write the `response` to it, closing the connection afterwards.
+fn respond(stream: TcpStream, response: &Response) -> Request {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+
+        match header.trim_end().split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("content-length") => length = value.trim().parse().unwrap(),
+            Some(_) => {},
+            None => break,
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+
+    let headers: String = response.headers.iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        response.status, response.body.len(), headers, response.body
+    ).unwrap();
+
+    let mut parts = request_line.split_whitespace();
+
+    Request {
+        method: parts.next().unwrap_or_default().to_string(),
+        path: parts.next().unwrap_or_default().to_string(),
+        body: String::from_utf8(body).unwrap(),
+    }
+}
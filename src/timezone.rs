@@ -0,0 +1,361 @@
+//! The time zones which Graph uses in `DateTimeTimeZone` values, eg: "Pacific Standard Time" or "Europe/Berlin".
+//!
+//! Graph usually names zones by their Windows names, which are mapped to the IANA zone CLDR gives for each,
+//! for the world ("001" territory). The rules of each zone, past and present, come from the IANA time zone
+//! database, by way of `chrono-tz`, so they're only as current as it is.
+//!
+//! See: <https://docs.microsoft.com/en-us/windows-hardware/manufacture/desktop/default-time-zones>
+//! See: <https://github.com/unicode-org/cldr/blob/main/common/supplemental/windowsZones.xml>
+
+use chrono::{Duration, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::{GapInfo, OffsetComponents, Tz, TZ_VARIANTS};
+
+/// A time zone, by the name Graph gave it, and the IANA zone whose rules it follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zone {
+    /// The Windows name of the zone, eg: "W. Europe Standard Time", or its IANA name, if no Windows zone maps to it.
+    pub name: &'static str,
+
+    /// The IANA name of the zone, eg: "Europe/Berlin".
+    pub iana: &'static str,
+
+    tz: Tz,
+}
+
+/// A change of a zone's offset from UTC, eg: the start of daylight saving time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// When the offset changes, in UTC.
+    pub utc: NaiveDateTime,
+
+    /// The offset before the change, in minutes.
+    pub from: i32,
+
+    /// The offset after the change, in minutes.
+    pub to: i32,
+
+    /// Is daylight saving time observed after the change?
+    pub daylight: bool,
+}
+
+impl Transition {
+    /// The local time at which the offset changes, as it was before the change, eg: 02:00 when clocks go forward to 03:00.
+    pub fn local(&self) -> NaiveDateTime {
+        self.utc + Duration::minutes(i64::from(self.from))
+    }
+}
+
+impl Zone {
+    /// The rules of the zone.
+    pub fn tz(&self) -> Tz {
+        self.tz
+    }
+
+    /// The offset from UTC, in minutes, at the given time in UTC, and whether daylight saving time is observed then.
+    pub fn offset_at(&self, utc: NaiveDateTime) -> (i32, bool) {
+        let offset = self.tz.offset_from_utc_datetime(&utc);
+
+        (offset.fix().local_minus_utc() / 60, !offset.dst_offset().is_zero())
+    }
+
+    /// Convert a local time in this zone to UTC.
+    ///
+    /// As in iCalendar, local times which are repeated when the offset goes back are taken as their first occurrence,
+    /// and those which are skipped when it goes forward, as being in the offset before the change.
+    pub fn local_to_utc(&self, local: NaiveDateTime) -> NaiveDateTime {
+        let offset = match self.tz.from_local_datetime(&local).earliest() {
+            Some(time) => time.offset().fix(),
+            None => match GapInfo::new(&local, &self.tz).and_then(|gap| gap.begin) {
+                Some((_, before)) => before.fix(),
+                None => self.tz.offset_from_utc_datetime(&local).fix(),
+            },
+        };
+
+        local - Duration::seconds(i64::from(offset.local_minus_utc()))
+    }
+
+    /// Every change of the zone's offset, or whether daylight saving time is observed, between `start` and `end`, in UTC.
+    ///
+    /// Offsets are compared a day apart, so a zone whose offset changes and changes back within a day is taken as not changing.
+    pub fn transitions(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        let mut time = start;
+        let mut offset = self.offset_at(time);
+
+        while time < end {
+            let next = (time + Duration::days(1)).min(end);
+            let next_offset = self.offset_at(next);
+
+            if next_offset != offset {
+                // Offsets only change on the minute, so narrow the change down to the minute it happens.
+                let (mut before, mut after) = (time, next);
+
+                while after - before > Duration::minutes(1) {
+                    let middle = before + Duration::minutes((after - before).num_minutes() / 2);
+
+                    if self.offset_at(middle) == offset {
+                        before = middle;
+                    } else {
+                        after = middle;
+                    }
+                }
+
+                let (to, daylight) = self.offset_at(after);
+                transitions.push(Transition { utc: after, from: offset.0, to, daylight });
+
+                // Start looking again from the change, in case there's another within the same day.
+                time = after;
+                offset = (to, daylight);
+                continue;
+            }
+
+            time = next;
+        }
+
+        transitions
+    }
+}
+
+/// Find a time zone by its Windows name, or any IANA name, regardless of case.
+///
+/// Graph returns IANA names instead of Windows names when they've been asked for with the
+/// `Prefer: outlook.timezone` header, or were used when the task was created.
+pub fn find(name: &str) -> Option<Zone> {
+    let name = name.trim();
+
+    if let Some((windows, iana)) = WINDOWS_ZONES.iter().find(|(windows, _)| windows.eq_ignore_ascii_case(name)) {
+        return Some(Zone { name: windows, iana, tz: iana.parse().ok()? });
+    }
+
+    let tz = *TZ_VARIANTS.iter().find(|tz| tz.name().eq_ignore_ascii_case(name))?;
+
+    // Zones which a Windows zone maps to are known by their Windows name, as they would be if Graph had sent it.
+    let windows = WINDOWS_ZONES.iter()
+        .find(|(_, iana)| *iana == tz.name())
+        .map_or(tz.name(), |(windows, _)| windows);
+
+    Some(Zone { name: windows, iana: tz.name(), tz })
+}
+
+/// Each Windows zone, and the IANA zone CLDR maps it to for the world, roughly ordered by offset.
+pub static WINDOWS_ZONES: &[(&str, &str)] = &[
+    ("Dateline Standard Time", "Etc/GMT+12"),
+    ("UTC-11", "Etc/GMT+11"),
+    ("Aleutian Standard Time", "America/Adak"),
+    ("Hawaiian Standard Time", "Pacific/Honolulu"),
+    ("Marquesas Standard Time", "Pacific/Marquesas"),
+    ("Alaskan Standard Time", "America/Anchorage"),
+    ("UTC-09", "Etc/GMT+9"),
+    ("Pacific Standard Time (Mexico)", "America/Tijuana"),
+    ("UTC-08", "Etc/GMT+8"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("US Mountain Standard Time", "America/Phoenix"),
+    ("Mountain Standard Time (Mexico)", "America/Mazatlan"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Yukon Standard Time", "America/Whitehorse"),
+    ("Central America Standard Time", "America/Guatemala"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Easter Island Standard Time", "Pacific/Easter"),
+    ("Central Standard Time (Mexico)", "America/Mexico_City"),
+    ("Canada Central Standard Time", "America/Regina"),
+    ("SA Pacific Standard Time", "America/Bogota"),
+    ("Eastern Standard Time (Mexico)", "America/Cancun"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("Haiti Standard Time", "America/Port-au-Prince"),
+    ("Cuba Standard Time", "America/Havana"),
+    ("US Eastern Standard Time", "America/Indianapolis"),
+    ("Turks And Caicos Standard Time", "America/Grand_Turk"),
+    ("Paraguay Standard Time", "America/Asuncion"),
+    ("Atlantic Standard Time", "America/Halifax"),
+    ("Venezuela Standard Time", "America/Caracas"),
+    ("Central Brazilian Standard Time", "America/Cuiaba"),
+    ("SA Western Standard Time", "America/La_Paz"),
+    ("Pacific SA Standard Time", "America/Santiago"),
+    ("Newfoundland Standard Time", "America/St_Johns"),
+    ("Tocantins Standard Time", "America/Araguaina"),
+    ("E. South America Standard Time", "America/Sao_Paulo"),
+    ("SA Eastern Standard Time", "America/Cayenne"),
+    ("Argentina Standard Time", "America/Buenos_Aires"),
+    ("Greenland Standard Time", "America/Godthab"),
+    ("Montevideo Standard Time", "America/Montevideo"),
+    ("Magallanes Standard Time", "America/Punta_Arenas"),
+    ("Saint Pierre Standard Time", "America/Miquelon"),
+    ("Bahia Standard Time", "America/Bahia"),
+    ("UTC-02", "Etc/GMT+2"),
+    ("Azores Standard Time", "Atlantic/Azores"),
+    ("Cape Verde Standard Time", "Atlantic/Cape_Verde"),
+    ("UTC", "Etc/UTC"),
+    ("GMT Standard Time", "Europe/London"),
+    ("Greenwich Standard Time", "Atlantic/Reykjavik"),
+    ("Sao Tome Standard Time", "Africa/Sao_Tome"),
+    ("Morocco Standard Time", "Africa/Casablanca"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("Central Europe Standard Time", "Europe/Budapest"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("Central European Standard Time", "Europe/Warsaw"),
+    ("W. Central Africa Standard Time", "Africa/Lagos"),
+    ("Jordan Standard Time", "Asia/Amman"),
+    ("GTB Standard Time", "Europe/Bucharest"),
+    ("Middle East Standard Time", "Asia/Beirut"),
+    ("Egypt Standard Time", "Africa/Cairo"),
+    ("E. Europe Standard Time", "Europe/Chisinau"),
+    ("Syria Standard Time", "Asia/Damascus"),
+    ("West Bank Standard Time", "Asia/Hebron"),
+    ("South Africa Standard Time", "Africa/Johannesburg"),
+    ("FLE Standard Time", "Europe/Kiev"),
+    ("Israel Standard Time", "Asia/Jerusalem"),
+    ("South Sudan Standard Time", "Africa/Juba"),
+    ("Kaliningrad Standard Time", "Europe/Kaliningrad"),
+    ("Sudan Standard Time", "Africa/Khartoum"),
+    ("Libya Standard Time", "Africa/Tripoli"),
+    ("Namibia Standard Time", "Africa/Windhoek"),
+    ("Arabic Standard Time", "Asia/Baghdad"),
+    ("Turkey Standard Time", "Europe/Istanbul"),
+    ("Arab Standard Time", "Asia/Riyadh"),
+    ("Belarus Standard Time", "Europe/Minsk"),
+    ("Russian Standard Time", "Europe/Moscow"),
+    ("E. Africa Standard Time", "Africa/Nairobi"),
+    ("Volgograd Standard Time", "Europe/Volgograd"),
+    ("Iran Standard Time", "Asia/Tehran"),
+    ("Arabian Standard Time", "Asia/Dubai"),
+    ("Astrakhan Standard Time", "Europe/Astrakhan"),
+    ("Azerbaijan Standard Time", "Asia/Baku"),
+    ("Russia Time Zone 3", "Europe/Samara"),
+    ("Mauritius Standard Time", "Indian/Mauritius"),
+    ("Saratov Standard Time", "Europe/Saratov"),
+    ("Georgian Standard Time", "Asia/Tbilisi"),
+    ("Caucasus Standard Time", "Asia/Yerevan"),
+    ("Afghanistan Standard Time", "Asia/Kabul"),
+    ("West Asia Standard Time", "Asia/Tashkent"),
+    ("Ekaterinburg Standard Time", "Asia/Yekaterinburg"),
+    ("Pakistan Standard Time", "Asia/Karachi"),
+    ("Qyzylorda Standard Time", "Asia/Qyzylorda"),
+    ("India Standard Time", "Asia/Calcutta"),
+    ("Sri Lanka Standard Time", "Asia/Colombo"),
+    ("Nepal Standard Time", "Asia/Katmandu"),
+    ("Central Asia Standard Time", "Asia/Bishkek"),
+    ("Bangladesh Standard Time", "Asia/Dhaka"),
+    ("Omsk Standard Time", "Asia/Omsk"),
+    ("Myanmar Standard Time", "Asia/Rangoon"),
+    ("SE Asia Standard Time", "Asia/Bangkok"),
+    ("Altai Standard Time", "Asia/Barnaul"),
+    ("W. Mongolia Standard Time", "Asia/Hovd"),
+    ("North Asia Standard Time", "Asia/Krasnoyarsk"),
+    ("N. Central Asia Standard Time", "Asia/Novosibirsk"),
+    ("Tomsk Standard Time", "Asia/Tomsk"),
+    ("China Standard Time", "Asia/Shanghai"),
+    ("North Asia East Standard Time", "Asia/Irkutsk"),
+    ("Singapore Standard Time", "Asia/Singapore"),
+    ("W. Australia Standard Time", "Australia/Perth"),
+    ("Taipei Standard Time", "Asia/Taipei"),
+    ("Ulaanbaatar Standard Time", "Asia/Ulaanbaatar"),
+    ("Aus Central W. Standard Time", "Australia/Eucla"),
+    ("Transbaikal Standard Time", "Asia/Chita"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("North Korea Standard Time", "Asia/Pyongyang"),
+    ("Korea Standard Time", "Asia/Seoul"),
+    ("Yakutsk Standard Time", "Asia/Yakutsk"),
+    ("Cen. Australia Standard Time", "Australia/Adelaide"),
+    ("AUS Central Standard Time", "Australia/Darwin"),
+    ("E. Australia Standard Time", "Australia/Brisbane"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+    ("West Pacific Standard Time", "Pacific/Port_Moresby"),
+    ("Tasmania Standard Time", "Australia/Hobart"),
+    ("Vladivostok Standard Time", "Asia/Vladivostok"),
+    ("Lord Howe Standard Time", "Australia/Lord_Howe"),
+    ("Bougainville Standard Time", "Pacific/Bougainville"),
+    ("Russia Time Zone 10", "Asia/Srednekolymsk"),
+    ("Magadan Standard Time", "Asia/Magadan"),
+    ("Norfolk Standard Time", "Pacific/Norfolk"),
+    ("Sakhalin Standard Time", "Asia/Sakhalin"),
+    ("Central Pacific Standard Time", "Pacific/Guadalcanal"),
+    ("Russia Time Zone 11", "Asia/Kamchatka"),
+    ("New Zealand Standard Time", "Pacific/Auckland"),
+    ("UTC+12", "Etc/GMT-12"),
+    ("Fiji Standard Time", "Pacific/Fiji"),
+    ("Chatham Islands Standard Time", "Pacific/Chatham"),
+    ("UTC+13", "Etc/GMT-13"),
+    ("Tonga Standard Time", "Pacific/Tongatapu"),
+    ("Samoa Standard Time", "Pacific/Apia"),
+    ("Line Islands Standard Time", "Pacific/Kiritimati"),
+];
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn every_windows_zone_has_rules() {
+        for (windows, iana) in WINDOWS_ZONES {
+            let zone = find(windows).unwrap_or_else(|| panic!("{} should be found", windows));
+            assert_eq!(zone.iana, *iana);
+        }
+    }
+
+    #[test]
+    fn any_iana_zone_is_found() {
+        let toronto = find("america/toronto").expect("America/Toronto should be found");
+        assert_eq!((toronto.name, toronto.iana), ("America/Toronto", "America/Toronto"));
+
+        let amsterdam = find(" Europe/Amsterdam ").expect("Europe/Amsterdam should be found");
+        assert_eq!(amsterdam.local_to_utc(at(2021, 7, 1, 12, 0)), at(2021, 7, 1, 10, 0));
+
+        // Zones which a Windows zone maps to are known by the Windows name.
+        assert_eq!(find("Europe/Berlin").map(|zone| zone.name), Some("W. Europe Standard Time"));
+        assert_eq!(find("Mars Standard Time"), None);
+    }
+
+    #[test]
+    fn local_times_around_changes_are_converted() {
+        let pacific = find("Pacific Standard Time").unwrap();
+
+        // Clocks went forward from 02:00 to 03:00 on the 14th of March 2021, and back from 02:00 to 01:00 on the 7th of November.
+        assert_eq!(pacific.local_to_utc(at(2021, 3, 14, 1, 59)), at(2021, 3, 14, 9, 59));
+        assert_eq!(pacific.local_to_utc(at(2021, 3, 14, 3, 0)), at(2021, 3, 14, 10, 0));
+        assert_eq!(pacific.local_to_utc(at(2021, 3, 14, 2, 30)), at(2021, 3, 14, 10, 30), "skipped times use the offset before the change");
+        assert_eq!(pacific.local_to_utc(at(2021, 11, 7, 1, 30)), at(2021, 11, 7, 8, 30), "repeated times are their first occurrence");
+        assert_eq!(pacific.local_to_utc(at(2021, 11, 7, 2, 0)), at(2021, 11, 7, 10, 0));
+    }
+
+    #[test]
+    fn zones_with_irregular_rules_are_converted() {
+        // Chile's daylight saving time starts on the first Sunday of September, at midnight, which is skipped.
+        let chile = find("Pacific SA Standard Time").unwrap();
+        assert_eq!(chile.local_to_utc(at(2023, 9, 3, 0, 30)), at(2023, 9, 3, 4, 30));
+        assert_eq!(chile.local_to_utc(at(2023, 9, 3, 1, 0)), at(2023, 9, 3, 4, 0));
+
+        // Paraguay has observed daylight saving time all year since October 2024.
+        let paraguay = find("Paraguay Standard Time").unwrap();
+        assert_eq!(paraguay.local_to_utc(at(2025, 7, 1, 12, 0)), at(2025, 7, 1, 15, 0));
+
+        // Greenland moved from -03:00 to -02:00 in March 2023, and has observed daylight saving time since 2024.
+        let greenland = find("Greenland Standard Time").unwrap();
+        assert_eq!(greenland.local_to_utc(at(2022, 1, 1, 12, 0)), at(2022, 1, 1, 15, 0));
+        assert_eq!(greenland.local_to_utc(at(2024, 1, 1, 12, 0)), at(2024, 1, 1, 14, 0));
+        assert_eq!(greenland.local_to_utc(at(2024, 7, 1, 12, 0)), at(2024, 7, 1, 13, 0));
+    }
+
+    #[test]
+    fn transitions_are_found_to_the_minute() {
+        let london = find("GMT Standard Time").unwrap();
+
+        assert_eq!(london.transitions(at(2021, 1, 1, 0, 0), at(2022, 1, 1, 0, 0)), vec![
+            Transition { utc: at(2021, 3, 28, 1, 0), from: 0, to: 60, daylight: true },
+            Transition { utc: at(2021, 10, 31, 1, 0), from: 60, to: 0, daylight: false },
+        ]);
+
+        let sydney = find("AUS Eastern Standard Time").unwrap();
+        let transitions = sydney.transitions(at(2021, 1, 1, 0, 0), at(2022, 1, 1, 0, 0));
+
+        assert_eq!(transitions.iter().map(Transition::local).collect::<Vec<_>>(), vec![at(2021, 4, 4, 3, 0), at(2021, 10, 3, 2, 0)]);
+        assert_eq!(transitions.iter().map(|t| (t.from, t.to, t.daylight)).collect::<Vec<_>>(), vec![(660, 600, false), (600, 660, true)]);
+
+        assert!(find("Tokyo Standard Time").unwrap().transitions(at(2000, 1, 1, 0, 0), at(2030, 1, 1, 0, 0)).is_empty());
+    }
+}